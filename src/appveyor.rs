@@ -1,8 +1,21 @@
 #![allow(missing_docs)]
 
-use env;
+use build::{Build, Job};
+use commit::Commit;
+use provenance::{self, Provenance};
+use provider::Provider;
+use pull_request::PullRequest;
+use repository::{Forge, Repository};
+use runner::Runner;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
+use trigger::Trigger;
+use vars::{Detect, Missing, Placeholder, Vars};
 
 /// Appveyor CI
 ///
@@ -11,6 +24,7 @@ use std::str::FromStr;
 /// - <https://docs.travis-ci.com/user/environment-variables/#Default-Environment-Variables>
 /// - <https://github.com/codecov/codecov-bash/blob/0c5cebde9e0c640c8f737a843309df15a9509e26/codecov#L658-L668>
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct Appveyor {
     pub api_url: String,
     pub account_name: String,
@@ -47,66 +61,536 @@ pub struct Appveyor {
     pub re_build: bool,
     pub platform: String,
     pub configuration: String,
-    non_exhaustive: (),
+    /// The link to the build, built from the account, project and build id.
+    build_url: Option<String>,
+    /// The required variables that were missing or invalid, in lenient detection.
+    missing: Missing,
 }
 
+impl_from_env!(Appveyor);
+
 impl Appveyor {
-    /// Construct this provider's information from the environment.
-    pub fn from_env() -> Option<Self> {
-        if !(env("APPVEYOR")? == "True" && env("CI")? == "True") {
+    /// Replace the build version shown by AppVeyor, e.g. with the version from `Cargo.toml`.
+    ///
+    /// On success, `build_version` is updated to the new version.
+    ///
+    /// # References
+    ///
+    /// - <https://www.appveyor.com/docs/build-worker-api/#update-build-details>
+    pub fn update_build_version(&mut self, version: &str) -> Result<(), ApiError> {
+        let body = format!("{{\"version\":{}}}", json_string(version));
+        api_request(&self.api_url, "PUT", "api/build", &body)?;
+        self.build_version = version.to_owned();
+        Ok(())
+    }
+
+    /// The link to a page of this project, unless a variable it is built from is missing.
+    fn project_url(&self, page: &str, id_var: &str, id: &str) -> Option<String> {
+        Some(format!(
+            "https://ci.appveyor.com/project/{}/{}/{}/{}",
+            self.missing
+                .given("APPVEYOR_ACCOUNT_NAME", &self.account_name)?,
+            self.missing
+                .given("APPVEYOR_PROJECT_SLUG", &self.project_slug)?,
+            page,
+            self.missing.given(id_var, id)?
+        ))
+    }
+
+    /// Set an environment variable that is visible to the following steps of the build.
+    ///
+    /// # References
+    ///
+    /// - <https://www.appveyor.com/docs/build-worker-api/#add-environment-variable>
+    pub fn set_variable(&self, name: &str, value: &str) -> Result<(), ApiError> {
+        let body = format!(
+            "{{\"name\":{},\"value\":{}}}",
+            json_string(name),
+            json_string(value)
+        );
+        api_request(&self.api_url, "POST", "api/build/variables", &body)
+    }
+}
+
+impl Detect for Appveyor {
+    const NAME: &'static str = "AppVeyor";
+
+    fn read(vars: &mut Vars) -> Option<Self> {
+        if !vars.markers(&[("APPVEYOR", Some("True")), ("CI", Some("True"))]) {
             return None;
         }
 
-        Some(Appveyor {
-            api_url: env("APPVEYOR_API_URL")?,
-            account_name: env("APPVEYOR_ACCOUNT_NAME")?,
-            project_id: env("APPVEYOR_PROJECT_ID")?,
-            project_name: env("APPVEYOR_PROJECT_NAME")?,
-            project_slug: env("APPVEYOR_PROJECT_SLUG")?,
-            build_folder: env("APPVEYOR_BUILD_FOLDER")?.into(),
-            build_id: env("APPVEYOR_BUILD_ID")?,
-            build_number: env("APPVEYOR_BUILD_NUMBER")?.parse().ok()?,
-            build_version: env("APPVEYOR_BUILD_VERSION")?,
-            build_worker_image: env("APPVEYOR_BUILD_WORKER_IMAGE")?,
-            pull_request_number: env("APPVEYOR_PULL_REQUEST_NUMBER").and_then(|it| it.parse().ok()),
-            pull_request_title: env("APPVEYOR_PULL_REQUEST_TITLE"),
-            pull_request_head_repo_name: env("APPVEYOR_PULL_REQUEST_HEAD_REPO_NAME"),
-            pull_request_head_repo_branch: env("APPVEYOR_PULL_REQUEST_HEAD_REPO_BRANCH"),
-            pull_request_head_commit: env("APVEYOR_PULL_REQUEST_HEAD_COMMIT"),
-            job_id: env("APPVEYOR_JOB_ID")?,
-            job_name: env("APPVEYOR_JOB_NAME")?,
-            job_number: env("APPVEYOR_JOB_NUMBER")?.parse().ok()?,
-            repo_provider: env("APPVEYOR_REPO_PROVIDER")?.parse().ok()?,
-            repo_scm: env("APPVEYOR_REPO_SCM")?.parse().ok()?,
-            repo_name: env("APPVEYOR_REPO_NAME")?,
-            repo_branch: env("APPVEYOR_REPO_BRANCH")?,
-            repo_tag: env("APVEYOR_REPO_TAG")?.parse().ok()?,
-            repo_tag_name: env("APPVEYOR_REPO_TAG_NAME"),
-            repo_commit: env("APPVEYOR_REPO_COMMIT")?,
-            repo_commit_author: env("APPVEYOR_REPO_COMMIT_AUTHOR")?,
-            repo_commit_author_email: env("APPVEYOR_REPO_COMMIT_AUTHOR_EMAIL")?,
-            repo_commit_timestamp: env("APPVEYOR_REPO_COMMIT_TIMESTAMP")?,
-            repo_commit_message: env("APPVEYOR_REPO_COMMIT_MESSAGE")?,
-            repo_commit_message_extended: env("APPVEYOR_REPO_COMMIT_MESSAGE_EXTENDED")?,
-            scheduled_build: env("APPVEYOR_SCHEDULED_BUILD").is_some(),
-            forced_build: env("APPVEYOR_FORCED_BUILD").is_some(),
-            re_build: env("APPVEYOR_RE_BUILD").is_some(),
-            platform: env("PLATFORM")?,
-            configuration: env("CONFIGURATION")?,
-            non_exhaustive: (),
+        let mut appveyor = Appveyor {
+            api_url: vars.required("APPVEYOR_API_URL"),
+            account_name: vars.required("APPVEYOR_ACCOUNT_NAME"),
+            project_id: vars.required("APPVEYOR_PROJECT_ID"),
+            project_name: vars.required("APPVEYOR_PROJECT_NAME"),
+            project_slug: vars.required("APPVEYOR_PROJECT_SLUG"),
+            build_folder: vars.required_path("APPVEYOR_BUILD_FOLDER"),
+            build_id: vars.required("APPVEYOR_BUILD_ID"),
+            build_number: vars.required("APPVEYOR_BUILD_NUMBER"),
+            build_version: vars.required("APPVEYOR_BUILD_VERSION"),
+            build_worker_image: vars.required("APPVEYOR_BUILD_WORKER_IMAGE"),
+            pull_request_number: vars.optional("APPVEYOR_PULL_REQUEST_NUMBER"),
+            pull_request_title: vars.optional("APPVEYOR_PULL_REQUEST_TITLE"),
+            pull_request_head_repo_name: vars.optional("APPVEYOR_PULL_REQUEST_HEAD_REPO_NAME"),
+            pull_request_head_repo_branch: vars.optional("APPVEYOR_PULL_REQUEST_HEAD_REPO_BRANCH"),
+            pull_request_head_commit: vars.optional("APPVEYOR_PULL_REQUEST_HEAD_COMMIT"),
+            job_id: vars.required("APPVEYOR_JOB_ID"),
+            job_name: vars.required("APPVEYOR_JOB_NAME"),
+            job_number: vars.required("APPVEYOR_JOB_NUMBER"),
+            repo_provider: vars.required("APPVEYOR_REPO_PROVIDER"),
+            repo_scm: vars.required("APPVEYOR_REPO_SCM"),
+            repo_name: vars.required("APPVEYOR_REPO_NAME"),
+            repo_branch: vars.required("APPVEYOR_REPO_BRANCH"),
+            repo_tag: vars.required("APPVEYOR_REPO_TAG"),
+            repo_tag_name: vars.optional("APPVEYOR_REPO_TAG_NAME"),
+            repo_commit: vars.required("APPVEYOR_REPO_COMMIT"),
+            repo_commit_author: vars.required("APPVEYOR_REPO_COMMIT_AUTHOR"),
+            repo_commit_author_email: vars.required("APPVEYOR_REPO_COMMIT_AUTHOR_EMAIL"),
+            repo_commit_timestamp: vars.required("APPVEYOR_REPO_COMMIT_TIMESTAMP"),
+            repo_commit_message: vars.required("APPVEYOR_REPO_COMMIT_MESSAGE"),
+            repo_commit_message_extended: vars.required("APPVEYOR_REPO_COMMIT_MESSAGE_EXTENDED"),
+            scheduled_build: vars.is_set("APPVEYOR_SCHEDULED_BUILD"),
+            forced_build: vars.is_set("APPVEYOR_FORCED_BUILD"),
+            re_build: vars.is_set("APPVEYOR_RE_BUILD"),
+            platform: vars.required("PLATFORM"),
+            configuration: vars.required("CONFIGURATION"),
+            build_url: None,
+            missing: vars.missing(),
+        };
+        appveyor.build_url =
+            appveyor.project_url("builds", "APPVEYOR_BUILD_ID", &appveyor.build_id);
+        Some(appveyor)
+    }
+}
+
+impl Provider for Appveyor {
+    fn name(&self) -> &str {
+        Appveyor::NAME
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.missing
+            .given("APPVEYOR_REPO_COMMIT", &*self.repo_commit)
+    }
+
+    fn commit(&self) -> Option<Commit> {
+        let mut commit = Commit::new(self.commit_sha()?);
+        commit.set_message(&format!(
+            "{}\n\n{}",
+            self.repo_commit_message, self.repo_commit_message_extended
+        ));
+        let missing = &self.missing;
+        commit.author_name = missing.given(
+            "APPVEYOR_REPO_COMMIT_AUTHOR",
+            self.repo_commit_author.clone(),
+        );
+        commit.author_email = missing.given(
+            "APPVEYOR_REPO_COMMIT_AUTHOR_EMAIL",
+            self.repo_commit_author_email.clone(),
+        );
+        commit.timestamp = missing.given(
+            "APPVEYOR_REPO_COMMIT_TIMESTAMP",
+            self.repo_commit_timestamp.clone(),
+        );
+        Some(commit)
+    }
+
+    fn branch(&self) -> Option<&str> {
+        if self.repo_tag {
+            None
+        } else if self.pull_request_number.is_some() {
+            self.pull_request_head_repo_branch.as_deref()
+        } else {
+            self.missing
+                .given("APPVEYOR_REPO_BRANCH", &*self.repo_branch)
+        }
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.repo_tag_name.as_deref()
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        let mut pr = PullRequest::new(self.pull_request_number?.into());
+        pr.title = self.pull_request_title.clone();
+        pr.source_branch = self.pull_request_head_repo_branch.clone();
+        pr.target_branch = self
+            .missing
+            .given("APPVEYOR_REPO_BRANCH", self.repo_branch.clone());
+        pr.head_sha = self.pull_request_head_commit.clone();
+        pr.head_repo = self.pull_request_head_repo_name.clone();
+        pr.is_fork = self
+            .pull_request_head_repo_name
+            .as_ref()
+            .filter(|_| !self.missing.contains("APPVEYOR_REPO_NAME"))
+            .map(|name| *name != self.repo_name);
+        Some(pr)
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.missing
+            .given("APPVEYOR_BUILD_NUMBER", u64::from(self.build_number))
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.build_url.as_deref()
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        self.missing
+            .given("APPVEYOR_REPO_NAME", self.repo_name.clone())
+    }
+
+    fn job_id(&self) -> Option<&str> {
+        self.missing.given("APPVEYOR_JOB_ID", &*self.job_id)
+    }
+
+    fn repository(&self) -> Option<Repository> {
+        let repo_name = self.repo_slug()?;
+        let host = match *self
+            .missing
+            .given("APPVEYOR_REPO_PROVIDER", &self.repo_provider)?
+        {
+            RepoProvider::Github => "github.com",
+            RepoProvider::BitBucket => "bitbucket.org",
+            RepoProvider::Gitlab => "gitlab.com",
+            RepoProvider::Vso => {
+                let mut repo = Repository::from_slug(&repo_name)?;
+                repo.forge = Some(Forge::AzureRepos);
+                return Some(repo);
+            }
+            RepoProvider::Kiln => return Repository::from_slug(&repo_name),
+        };
+        Repository::on_host(host, &repo_name)
+    }
+
+    fn build(&self) -> Build {
+        Build {
+            id: self
+                .missing
+                .given("APPVEYOR_BUILD_ID", self.build_id.clone()),
+            number: self.build_number(),
+            url: self.build_url.clone(),
+            // AppVeyor only says whether this is a rebuild, not how many came before it.
+            attempt: if self.re_build { None } else { Some(1) },
+            ..Build::default()
+        }
+    }
+
+    fn job(&self) -> Option<Job> {
+        let job = Job {
+            id: self.job_id().map(str::to_owned),
+            name: self
+                .missing
+                .given("APPVEYOR_JOB_NAME", self.job_name.clone()),
+            number: self
+                .missing
+                .given("APPVEYOR_JOB_NUMBER", self.job_number.to_string()),
+            url: self.project_url("build/job", "APPVEYOR_JOB_ID", &self.job_id),
+        };
+        Some(job).filter(|job| *job != Job::default())
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        Some(if self.scheduled_build {
+            Trigger::Schedule
+        } else if self.re_build {
+            Trigger::Rebuild
+        } else if self.forced_build {
+            Trigger::Manual
+        } else if self.pull_request_number.is_some() {
+            Trigger::PullRequest
+        } else if self.repo_tag {
+            Trigger::Tag
+        } else {
+            Trigger::Push
         })
     }
+
+    fn runner(&self) -> Runner {
+        let mut runner = Runner::new();
+        let image = self.build_worker_image.to_ascii_lowercase();
+        if image.contains("ubuntu") {
+            runner.os = "linux".to_owned();
+        } else if image.contains("macos") {
+            runner.os = "macos".to_owned();
+        } else if image.contains("visual studio") {
+            runner.os = "windows".to_owned();
+        }
+        match &*self.platform.to_ascii_lowercase() {
+            "x64" => runner.arch = "x86_64".to_owned(),
+            "x86" | "win32" => runner.arch = "x86".to_owned(),
+            "arm64" => runner.arch = "aarch64".to_owned(),
+            _ => {}
+        }
+        runner.image = self.missing.given(
+            "APPVEYOR_BUILD_WORKER_IMAGE",
+            self.build_worker_image.clone(),
+        );
+        runner
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        if !provenance::has_value(self, field) {
+            return None;
+        }
+        match field {
+            "commit_sha" => provenance::var("APPVEYOR_REPO_COMMIT"),
+            "commit" => provenance::derived(
+                "APPVEYOR_REPO_COMMIT, APPVEYOR_REPO_COMMIT_MESSAGE, \
+                 APPVEYOR_REPO_COMMIT_MESSAGE_EXTENDED, APPVEYOR_REPO_COMMIT_AUTHOR, \
+                 APPVEYOR_REPO_COMMIT_AUTHOR_EMAIL and APPVEYOR_REPO_COMMIT_TIMESTAMP",
+            ),
+            "branch" if self.branch().is_none() => None,
+            "branch" if self.pull_request_number.is_some() => {
+                provenance::var("APPVEYOR_PULL_REQUEST_HEAD_REPO_BRANCH")
+            }
+            "branch" => provenance::var("APPVEYOR_REPO_BRANCH"),
+            "tag" if self.repo_tag_name.is_some() => provenance::var("APPVEYOR_REPO_TAG_NAME"),
+            "pull_request" if self.pull_request_number.is_some() => {
+                provenance::var("APPVEYOR_PULL_REQUEST_NUMBER")
+            }
+            "trigger" => provenance::derived(
+                "APPVEYOR_SCHEDULED_BUILD, APPVEYOR_RE_BUILD, APPVEYOR_FORCED_BUILD, \
+                 APPVEYOR_PULL_REQUEST_NUMBER and APPVEYOR_REPO_TAG",
+            ),
+            "build_number" => provenance::var("APPVEYOR_BUILD_NUMBER"),
+            "build_url" => provenance::derived(
+                "APPVEYOR_ACCOUNT_NAME, APPVEYOR_PROJECT_SLUG and APPVEYOR_BUILD_ID",
+            ),
+            "build" => provenance::derived(
+                "APPVEYOR_BUILD_ID, APPVEYOR_BUILD_NUMBER, APPVEYOR_ACCOUNT_NAME, \
+                 APPVEYOR_PROJECT_SLUG and APPVEYOR_RE_BUILD",
+            ),
+            "repo_slug" => provenance::var("APPVEYOR_REPO_NAME"),
+            "repository" if self.repository().is_some() => {
+                provenance::derived("APPVEYOR_REPO_PROVIDER and APPVEYOR_REPO_NAME")
+            }
+            "job_id" => provenance::var("APPVEYOR_JOB_ID"),
+            "job" => {
+                provenance::derived("APPVEYOR_JOB_ID, APPVEYOR_JOB_NAME and APPVEYOR_JOB_NUMBER")
+            }
+            "runner"
+                if !self.missing.contains("APPVEYOR_BUILD_WORKER_IMAGE")
+                    || !self.missing.contains("PLATFORM") =>
+            {
+                provenance::derived("APPVEYOR_BUILD_WORKER_IMAGE and PLATFORM")
+            }
+            _ => provenance::default(self, field),
+        }
+    }
+}
+
+/// How long to wait for the build worker before giving up, so a hung API doesn't hang the build.
+const API_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn api_request(api_url: &str, method: &str, path: &str, body: &str) -> Result<(), ApiError> {
+    let (authority, base) = split_api_url(api_url)?;
+    let mut stream = connect(authority)?;
+    stream.set_read_timeout(Some(API_TIMEOUT))?;
+    stream.set_write_timeout(Some(API_TIMEOUT))?;
+
+    let separator = if base.ends_with('/') { "" } else { "/" };
+    let request = format!(
+        "{} {}{}{} HTTP/1.1\r\n\
+         Host: {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {}",
+        method,
+        base,
+        separator,
+        path,
+        authority,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    check_response(&response)
+}
+
+/// Connect to `host` or `host:port`, trying every address it resolves to.
+///
+/// IPv6 addresses are written in brackets, like `[::1]:1033`.
+fn connect(authority: &str) -> io::Result<TcpStream> {
+    let addrs = match split_port(authority) {
+        (_, Some(_)) => authority.to_socket_addrs()?,
+        (host, None) => (host, 80).to_socket_addrs()?,
+    };
+    let mut last_err = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, API_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("could not resolve {}", authority),
+        )
+    }))
+}
+
+/// Split `host:port` into the host, without brackets around an IPv6 address, and the port.
+fn split_port(authority: &str) -> (&str, Option<&str>) {
+    if let Some(rest) = authority.strip_prefix('[') {
+        return match rest.find(']') {
+            Some(end) => (&rest[..end], rest[end + 1..].strip_prefix(':')),
+            None => (authority, None),
+        };
+    }
+    match authority.rfind(':') {
+        Some(colon) => (&authority[..colon], Some(&authority[colon + 1..])),
+        None => (authority, None),
+    }
+}
+
+/// Turn an HTTP response into an error, unless its status is a success.
+fn check_response(response: &[u8]) -> Result<(), ApiError> {
+    let response = String::from_utf8_lossy(response);
+    let (head, body) = match response.find("\r\n\r\n") {
+        Some(i) => (&response[..i], &response[i + 4..]),
+        None => (&*response, ""),
+    };
+    let mut lines = head.lines();
+    let code = lines
+        .next()
+        .and_then(|status| status.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or(ApiError::InvalidResponse)?;
+    if (200..300).contains(&code) {
+        return Ok(());
+    }
+    let chunked = lines.any(|header| match header.split_once(':') {
+        Some((name, value)) => {
+            name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.to_ascii_lowercase().contains("chunked")
+        }
+        None => false,
+    });
+    let body = if chunked {
+        decode_chunked(body).ok_or(ApiError::InvalidResponse)?
+    } else {
+        body.to_owned()
+    };
+    Err(ApiError::Status { code, body })
+}
+
+/// Join the chunks of a body sent with `Transfer-Encoding: chunked`.
+fn decode_chunked(mut body: &str) -> Option<String> {
+    let mut decoded = String::new();
+    loop {
+        let (size, rest) = body.split_once("\r\n")?;
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.push_str(rest.get(..size)?);
+        body = rest.get(size..)?.strip_prefix("\r\n")?;
+    }
+}
+
+/// Split `http://host:port/base/` into its authority and base path.
+fn split_api_url(url: &str) -> Result<(&str, &str), ApiError> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| ApiError::InvalidUrl(url.to_owned()))?;
+    let (authority, base) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(ApiError::InvalidUrl(url.to_owned()));
+    }
+    Ok((authority, base))
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// An error from the AppVeyor build worker API.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ApiError {
+    /// `APPVEYOR_API_URL` is not an `http://` URL.
+    InvalidUrl(String),
+    /// Talking to the build worker failed, or it did not answer in time.
+    Io(io::Error),
+    /// The build worker did not answer with an HTTP response.
+    InvalidResponse,
+    /// The build worker rejected the request.
+    Status {
+        /// The HTTP status code.
+        code: u16,
+        /// The body of the response, usually explaining the rejection.
+        body: String,
+    },
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApiError::InvalidUrl(ref url) => write!(f, "invalid AppVeyor API URL `{}`", url),
+            ApiError::Io(ref err) => write!(f, "could not reach the AppVeyor API: {}", err),
+            ApiError::InvalidResponse => write!(f, "invalid response from the AppVeyor API"),
+            ApiError::Status { code, ref body } if body.is_empty() => {
+                write!(f, "AppVeyor API responded with status {}", code)
+            }
+            ApiError::Status { code, ref body } => {
+                write!(f, "AppVeyor API responded with status {}: {}", code, body)
+            }
+        }
+    }
+}
+
+impl Error for ApiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ApiError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ApiError {
+    fn from(err: io::Error) -> Self {
+        ApiError::Io(err)
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
 pub enum RepoProvider {
     Github,
     BitBucket,
     Kiln,
     Vso,
     Gitlab,
-    #[doc(hidden)]
-    __NonExhaustive,
 }
 
 impl FromStr for RepoProvider {
@@ -124,12 +608,17 @@ impl FromStr for RepoProvider {
     }
 }
 
+impl Placeholder for RepoProvider {
+    fn placeholder() -> Self {
+        RepoProvider::Github
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
 pub enum RepoSCM {
     Git,
     Mercurial,
-    #[doc(hidden)]
-    __NonExhaustive,
 }
 
 impl FromStr for RepoSCM {
@@ -143,3 +632,87 @@ impl FromStr for RepoSCM {
         })
     }
 }
+
+impl Placeholder for RepoSCM {
+    fn placeholder() -> Self {
+        RepoSCM::Git
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string("1.0.0"), r#""1.0.0""#);
+        assert_eq!(json_string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        assert_eq!(json_string("a\nb\tc\r"), r#""a\nb\tc\r""#);
+        assert_eq!(json_string("\u{1}"), r#""\u0001""#);
+        assert_eq!(json_string("é"), r#""é""#);
+    }
+
+    #[test]
+    fn split_api_url_parts() {
+        assert_eq!(
+            split_api_url("http://localhost:1033/").unwrap(),
+            ("localhost:1033", "/")
+        );
+        assert_eq!(
+            split_api_url("http://localhost:1033/api/").unwrap(),
+            ("localhost:1033", "/api/")
+        );
+        assert_eq!(split_api_url("http://worker").unwrap(), ("worker", "/"));
+        assert!(split_api_url("https://localhost:1033/").is_err());
+        assert!(split_api_url("http:///").is_err());
+    }
+
+    #[test]
+    fn split_ports() {
+        assert_eq!(split_port("localhost:1033"), ("localhost", Some("1033")));
+        assert_eq!(split_port("worker"), ("worker", None));
+        assert_eq!(split_port("[::1]:8080"), ("::1", Some("8080")));
+        assert_eq!(split_port("[::1]"), ("::1", None));
+        assert_eq!(
+            split_api_url("http://[::1]:8080/api/").unwrap(),
+            ("[::1]:8080", "/api/")
+        );
+    }
+
+    #[test]
+    fn connect_ipv6() {
+        let listener = match ::std::net::TcpListener::bind("[::1]:0") {
+            Ok(listener) => listener,
+            // No IPv6 loopback on this machine.
+            Err(_) => return,
+        };
+        let port = listener.local_addr().unwrap().port();
+        assert!(connect(&format!("[::1]:{}", port)).is_ok());
+    }
+
+    #[test]
+    fn check_response_status() {
+        assert!(check_response(b"HTTP/1.1 204 No Content\r\n\r\n").is_ok());
+        assert!(check_response(b"garbage").is_err());
+        match check_response(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 3\r\n\r\nbad") {
+            Err(ApiError::Status { code: 400, body }) => assert_eq!(body, "bad"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn check_response_chunked() {
+        let response = b"HTTP/1.1 500 Internal Server Error\r\n\
+            Transfer-Encoding: chunked\r\n\r\n\
+            4\r\nout \r\n7;ext=1\r\nof disk\r\n0\r\n\r\n";
+        match check_response(response) {
+            Err(ApiError::Status { code: 500, body }) => assert_eq!(body, "out of disk"),
+            other => panic!("{:?}", other),
+        }
+        let truncated = b"HTTP/1.1 500 Oops\r\nTransfer-Encoding: chunked\r\n\r\nff\r\nab";
+        assert!(matches!(
+            check_response(truncated),
+            Err(ApiError::InvalidResponse)
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+/// The build being run, normalized across providers.
+///
+/// A build is one run of the whole pipeline, and consists of one or more [`Job`](struct.Job.html)s.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Build {
+    /// The id the provider uses internally for the build.
+    pub id: Option<String>,
+    /// The number of the build, as shown by the provider.
+    pub number: Option<u64>,
+    /// A link to the build.
+    pub url: Option<String>,
+    /// How often this build has been attempted, starting at 1.
+    ///
+    /// This is `None` if the provider doesn't say, or only says that this is a rebuild.
+    pub attempt: Option<u32>,
+}
+
+/// The job being run within a build, normalized across providers.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Job {
+    /// The id the provider uses internally for the job.
+    pub id: Option<String>,
+    /// The name of the job.
+    pub name: Option<String>,
+    /// The number of the job, as shown by the provider, e.g. `4.1`.
+    pub number: Option<String>,
+    /// A link to the job.
+    pub url: Option<String>,
+}
@@ -0,0 +1,271 @@
+use error::{Candidate, DetectError, Partial, Warning};
+use generic::Generic;
+use overrides::{Detected, Wanted};
+use provider::{CustomDetector, Provider};
+use source::EnvSource;
+use std::cell::Cell;
+use std::sync::{PoisonError, RwLock};
+use std::thread::LocalKey;
+use vars::{detect, detect_lenient, Detect};
+use CI;
+
+/// One link of the detection chain.
+pub(crate) trait Detector {
+    /// Strict detection; `Err(None)` means there is nothing to explain.
+    fn strict(&self, source: &dyn EnvSource) -> Result<CI, Option<Candidate>>;
+
+    fn lenient(&self, source: &dyn EnvSource) -> Option<Partial<CI>>;
+
+    /// The name of the provider, if it is known without detecting it.
+    fn name(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+struct BuiltIn<T>(fn(Detected<T>) -> CI);
+
+impl<T: Detect> Detector for BuiltIn<T> {
+    fn strict(&self, source: &dyn EnvSource) -> Result<CI, Option<Candidate>> {
+        detect(source).map(Detected::new).map(self.0).map_err(Some)
+    }
+
+    fn lenient(&self, source: &dyn EnvSource) -> Option<Partial<CI>> {
+        detect_lenient(source).map(|it| it.map(Detected::new).map(self.0))
+    }
+
+    fn name(&self) -> Option<&'static str> {
+        Some(T::NAME)
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Custom {
+    priority: i32,
+    detect: CustomDetector,
+}
+
+thread_local! {
+    /// Whether this thread is running a custom detector.
+    static IN_CUSTOM: Cell<bool> = const { Cell::new(false) };
+}
+
+impl Custom {
+    /// Run the detector, unless this thread is already running one:
+    /// a custom detector that detects the CI itself only sees the other providers.
+    fn detect(&self, source: &dyn EnvSource) -> Option<Box<dyn Provider>> {
+        let _reentry = Reentry::enter(&IN_CUSTOM)?;
+        (self.detect)(source)
+    }
+}
+
+impl Detector for Custom {
+    fn strict(&self, source: &dyn EnvSource) -> Result<CI, Option<Candidate>> {
+        self.detect(source)
+            .map(Detected::new)
+            .map(CI::Custom)
+            .ok_or(None)
+    }
+
+    fn lenient(&self, source: &dyn EnvSource) -> Option<Partial<CI>> {
+        Some(Partial {
+            provider: CI::Custom(Detected::new(self.detect(source)?)),
+            confidence: 1.0,
+            warnings: Vec::new(),
+        })
+    }
+}
+
+/// Sets a thread-local flag until dropped, even if the code it guards panics.
+pub(crate) struct Reentry(&'static LocalKey<Cell<bool>>);
+
+impl Reentry {
+    /// Set `flag`, or return `None` if it is already set on this thread.
+    pub(crate) fn enter(flag: &'static LocalKey<Cell<bool>>) -> Option<Self> {
+        if flag.with(|it| it.replace(true)) {
+            None
+        } else {
+            Some(Reentry(flag))
+        }
+    }
+}
+
+impl Drop for Reentry {
+    fn drop(&mut self) {
+        self.0.with(|it| it.set(false));
+    }
+}
+
+/// The provider declared in the config file.
+#[cfg(feature = "config")]
+struct FromConfig(::Config);
+
+#[cfg(feature = "config")]
+impl FromConfig {
+    fn detect(&self, source: &dyn EnvSource) -> Option<Partial<CI>> {
+        let partial = self.0.detect_lenient(source)?;
+        Some(partial.map(|declared| {
+            let declared: Box<dyn Provider> = Box::new(declared);
+            CI::Custom(Detected::new(declared))
+        }))
+    }
+}
+
+#[cfg(feature = "config")]
+impl Detector for FromConfig {
+    fn strict(&self, source: &dyn EnvSource) -> Result<CI, Option<Candidate>> {
+        self.detect(source)
+            .map(|partial| partial.provider)
+            .ok_or(None)
+    }
+
+    fn lenient(&self, source: &dyn EnvSource) -> Option<Partial<CI>> {
+        self.detect(source)
+    }
+}
+
+/// The config `source` points to; a [`Snapshot`](::Snapshot) only loads it once.
+#[cfg(feature = "config")]
+fn load_config(source: &dyn EnvSource) -> Result<Option<::Config>, String> {
+    match source.snapshot() {
+        Some(snapshot) => snapshot.config().clone(),
+        None => ::Config::load_from(source).map_err(|err| err.to_string()),
+    }
+}
+
+/// Registered providers, highest priority first.
+static CUSTOM: RwLock<Vec<Custom>> = RwLock::new(Vec::new());
+
+pub(crate) fn register(priority: i32, detect: CustomDetector) {
+    let mut custom = CUSTOM.write().unwrap_or_else(PoisonError::into_inner);
+    let index = custom
+        .iter()
+        .position(|it| it.priority < priority)
+        .unwrap_or(custom.len());
+    custom.insert(index, Custom { priority, detect });
+}
+
+/// The detectors for one source, in detection order.
+struct Chain {
+    detectors: Vec<Box<dyn Detector>>,
+    /// Why the config file could not be loaded, if it couldn't.
+    config_error: Option<String>,
+}
+
+impl Chain {
+    /// Report the config that could not be loaded on a lenient result.
+    fn warn(&self, mut partial: Partial<CI>) -> Partial<CI> {
+        if let Some(ref err) = self.config_error {
+            partial.warnings.insert(0, Warning::Config(err.clone()));
+        }
+        partial
+    }
+}
+
+/// All detectors for `source`, in detection order.
+#[rustfmt::skip]
+fn chain(source: &dyn EnvSource) -> Chain {
+    let custom = CUSTOM.read().unwrap_or_else(PoisonError::into_inner).clone();
+    let (before, after): (Vec<_>, Vec<_>) = custom.into_iter().partition(|it| it.priority > 0);
+
+    let mut chain: Vec<Box<dyn Detector>> = Vec::new();
+    #[cfg(feature = "config")]
+    let config_error = match load_config(source) {
+        Ok(config) => {
+            chain.extend(config.map(|it| Box::new(FromConfig(it)) as Box<dyn Detector>));
+            None
+        }
+        Err(err) => Some(err),
+    };
+    #[cfg(not(feature = "config"))]
+    let config_error = {
+        let _ = source;
+        None
+    };
+    chain.extend(before.into_iter().map(|it| Box::new(it) as Box<dyn Detector>));
+    chain.push(Box::new(BuiltIn(CI::Jenkins  )));
+    chain.push(Box::new(BuiltIn(CI::Travis   )));
+    chain.push(Box::new(BuiltIn(CI::DockerHub)));
+    chain.push(Box::new(BuiltIn(CI::Codeship )));
+    chain.push(Box::new(BuiltIn(CI::Codefresh)));
+    chain.push(Box::new(BuiltIn(CI::Circle   )));
+    chain.push(Box::new(BuiltIn(CI::Appveyor )));
+    chain.extend(after.into_iter().map(|it| Box::new(it) as Box<dyn Detector>));
+    chain.push(Box::new(BuiltIn(CI::Generic  )));
+    Chain { detectors: chain, config_error }
+}
+
+/// The detectors `CI_DETECTIVE_PROVIDER` allows, in detection order.
+fn allowed(wanted: &Wanted, source: &dyn EnvSource) -> Chain {
+    match *wanted {
+        Wanted::Any => chain(source),
+        Wanted::Nothing => Chain {
+            detectors: Vec::new(),
+            config_error: None,
+        },
+        Wanted::Named(_) => {
+            let mut chain = chain(source);
+            chain.detectors.retain(|detector| match detector.name() {
+                Some(name) => name == Generic::NAME || wanted.accepts(name),
+                None => true,
+            });
+            chain
+        }
+    }
+}
+
+/// Whether `CI_DETECTIVE_PROVIDER` allows what `detector` detected.
+///
+/// Other detectors with a static name were already filtered on it by [`allowed`].
+/// [`Generic`] is asked for by its own name, or by the name it reports from `CI_NAME`.
+fn accepts(wanted: &Wanted, detector: &dyn Detector, ci: &CI) -> bool {
+    match detector.name() {
+        Some(name) if name == Generic::NAME => wanted.accepts(name) || wanted.accepts(ci.name()),
+        Some(_) => true,
+        None => wanted.accepts(ci.name()),
+    }
+}
+
+/// The first provider that is detected strictly.
+pub(crate) fn strict(source: &dyn EnvSource) -> Result<CI, DetectError> {
+    let wanted = Wanted::from_source(source);
+    let chain = allowed(&wanted, source);
+    let mut error = DetectError::default().config(chain.config_error);
+    for detector in chain.detectors {
+        // A provider identified by its markers explains the failure better than the fallback.
+        if detector.name() == Some(Generic::NAME) && error.identified() {
+            break;
+        }
+        match detector.strict(source) {
+            Ok(ci) if accepts(&wanted, &*detector, &ci) => return Ok(ci),
+            Ok(_) | Err(None) => {}
+            Err(Some(candidate)) => error.push(candidate),
+        }
+    }
+    Err(error.wanted(wanted))
+}
+
+/// The first provider whose markers are found.
+pub(crate) fn lenient(source: &dyn EnvSource) -> Option<Partial<CI>> {
+    let wanted = Wanted::from_source(source);
+    let chain = allowed(&wanted, source);
+    let partial = chain
+        .detectors
+        .iter()
+        .filter_map(|detector| Some((detector, detector.lenient(source)?)))
+        .find(|&(detector, ref partial)| accepts(&wanted, &**detector, &partial.provider))?
+        .1;
+    Some(chain.warn(partial))
+}
+
+/// Every provider whose markers are found, in detection order.
+pub(crate) fn all(source: &dyn EnvSource) -> Vec<Partial<CI>> {
+    let wanted = Wanted::from_source(source);
+    let chain = allowed(&wanted, source);
+    chain
+        .detectors
+        .iter()
+        .filter_map(|detector| Some((detector, detector.lenient(source)?)))
+        .filter(|&(detector, ref partial)| accepts(&wanted, &**detector, &partial.provider))
+        .map(|(_, partial)| chain.warn(partial))
+        .collect()
+}
@@ -1,4 +1,12 @@
-use env;
+use build::Job;
+use parallelism::Parallelism;
+use provenance::{self, Provenance};
+use provider::Provider;
+use pull_request::PullRequest;
+use repository::Repository;
+use runner::{Hosting, Runner};
+use trigger::Trigger;
+use vars::{Detect, Missing, Vars};
 
 /// Circle CI
 ///
@@ -7,97 +15,227 @@ use env;
 /// - <https://circleci.com/docs/1.0/environment-variables/>
 /// - <https://github.com/codecov/codecov-bash/blob/8b76995ad4a95a61cecd4b049a448a402d91d197/codecov#L548-L568>
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[non_exhaustive]
 pub struct Circle {
     /// The username or organization name of the project being tested,
     /// i.e. `foo` in `circleci.com/gh/foo/bar/123`
-    project_username: String,
+    pub project_username: String,
     /// The repository name of the project being tested,
     /// i.e. `bar` in `circleci.com/gh/foo/bar/123`
-    project_reponame: String,
+    pub project_reponame: String,
     /// The name of the Git branch being tested, e.g. `master`,
     /// if the build is running for a branch.
-    branch: Option<String>,
+    pub branch: Option<String>,
     /// The name of the git tag being tested, e.g. `release-v1.5.4`,
     /// if the build is running [for a tag](https://circleci.com/docs/1.0/configuration/#tags).
-    tag: Option<String>,
+    pub tag: Option<String>,
     /// The SHA1 of the commit being tested.
-    sha1: String,
+    pub sha1: String,
     /// A link to the homepage for the current repository,
     /// for example, `https://github.com/circleci/frontend`.
-    repository_url: String,
+    pub repository_url: String,
     /// A link to GitHub’s comparison view for this push.
     /// Not present for builds that are triggered by GitHub pushes.
-    compare_url: Option<String>,
+    pub compare_url: Option<String>,
     /// A permanent link to the current build,
     /// for example, `https://circleci.com/gh/circleci/frontend/933`.
-    build_url: String,
+    pub build_url: String,
     /// The build number, same as in `circleci.com/gh/foo/bar/123`
-    build_num: usize,
+    pub build_num: usize,
     /// The build number of the previous build, same as in `circleci.com/gh/foo/bar/123`
-    previous_build_num: Option<usize>,
+    pub previous_build_num: Option<usize>,
     /// Comma-separated list of pull requests this build is a part of.
-    pull_requests: Option<String>,
+    pub pull_requests: Option<String>,
     /// If this build is part of only one pull request, its URL will be populated here. If there was
     /// more than one pull request, it will contain one of the pull request URLs (picked randomly).
-    pull_request: Option<String>,
+    pub pull_request: Option<String>,
     /// The directory whose contents are automatically saved as
     /// [build artifacts](https://circleci.com/docs/1.0/build-artifacts/).
-    artifacts: String,
+    pub artifacts: String,
     /// The GitHub login of the user who either pushed the code
     /// to GitHub or triggered the build from the UI/API.
-    username: String,
+    pub username: String,
     /// The directory whose contents are automatically processed as
     /// [JUnit test metadata](https://circleci.com/docs/1.0/test-metadata/).
-    test_reports: String,
+    pub test_reports: String,
     /// When the build is a part of a pull request from a fork,
     /// The username of the owner of the fork.
-    pr_username: Option<String>,
+    pub pr_username: Option<String>,
     /// When the build is a part of a pull request from a fork,
     /// The name of the repository the pull request was submitted from.
-    pr_reponame: Option<String>,
+    pub pr_reponame: Option<String>,
     /// When the build is a part of a pull request from a fork,
     /// The number of the pull request this build forms part of.
-    pr_number: Option<usize>,
+    pub pr_number: Option<usize>,
     /// The total number of nodes across which the current test is running.
-    node_total: usize,
+    pub node_total: usize,
     /// The index (0-based) of the current node.
-    node_index: usize,
+    pub node_index: usize,
     /// The build image this build runs on.
-    build_image: String,
-    non_exhaustive: (),
+    /// (CircleCI 1.0 only)
+    pub build_image: Option<String>,
+    /// The name of the current job, as configured in the workflow.
+    /// (CircleCI 2.0 only)
+    pub job: Option<String>,
+    /// The id CircleCI uses internally for the current job within its workflow.
+    /// (CircleCI 2.0 only)
+    pub workflow_job_id: Option<String>,
+    /// The required variables that were missing or invalid, in lenient detection.
+    missing: Missing,
 }
 
-impl Circle {
-    /// Construct this provider's information from the environment.
-    pub fn from_env() -> Option<Self> {
-        if !(env("CI")? == "true" && env("CIRCLECI")? == "true") {
+impl_from_env!(Circle);
+
+impl Detect for Circle {
+    const NAME: &'static str = "CircleCI";
+
+    fn read(vars: &mut Vars) -> Option<Self> {
+        if !vars.markers(&[("CI", Some("true")), ("CIRCLECI", Some("true"))]) {
             return None;
         }
 
         Some(Circle {
-            project_username: env("CIRCLE_PROJECT_USERNAME")?,
-            project_reponame: env("CIRCLE_PROJECT_REPONAME")?,
-            branch: env("CIRCLE_BRANCH"),
-            tag: env("CIRCLE_TAG"),
-            sha1: env("CIRCLE_SHA1")?,
-            repository_url: env("CIRCLE_REPOSITORY_URL")?,
-            compare_url: env("CIRCLE_COMPARE_URL"),
-            build_url: env("CIRCLE_BUILD_URL")?,
-            build_num: env("CIRCLE_BUILD_NUM")?.parse().ok()?,
-            previous_build_num: env("CIRCLE_PREVIOUS_BUILD_NUM").and_then(|it| it.parse().ok()),
-            pull_requests: env("CI_PULL_REQUESTS"),
-            pull_request: env("CI_PULL_REQUEST"),
-            artifacts: env("CIRCLE_ARTIFACTS")?,
-            username: env("CIRCLE_USERNAME")?,
-            test_reports: env("CIRCLE_TEST_REPORTS")?,
-            pr_username: env("CIRCLE_PR_USERNAME"),
-            pr_reponame: env("CIRCLE_PR_REPONAME"),
-            pr_number: env("CIRCLE_PR_NUMBER").and_then(|it| it.parse().ok()),
-            node_total: env("CIRCLE_NODE_TOTAL")?.parse().ok()?,
-            node_index: env("CIRCLE_NODE_INDEX")?.parse().ok()?,
-            build_image: env("CIRCLE_BUILD_IMAGE")?,
-            non_exhaustive: (),
+            project_username: vars.required("CIRCLE_PROJECT_USERNAME"),
+            project_reponame: vars.required("CIRCLE_PROJECT_REPONAME"),
+            branch: vars.optional("CIRCLE_BRANCH"),
+            tag: vars.optional("CIRCLE_TAG"),
+            sha1: vars.required("CIRCLE_SHA1"),
+            repository_url: vars.required("CIRCLE_REPOSITORY_URL"),
+            compare_url: vars.optional("CIRCLE_COMPARE_URL"),
+            build_url: vars.required("CIRCLE_BUILD_URL"),
+            build_num: vars.required("CIRCLE_BUILD_NUM"),
+            previous_build_num: vars.optional("CIRCLE_PREVIOUS_BUILD_NUM"),
+            pull_requests: vars.optional("CI_PULL_REQUESTS"),
+            pull_request: vars.optional("CI_PULL_REQUEST"),
+            artifacts: vars.required("CIRCLE_ARTIFACTS"),
+            username: vars.required("CIRCLE_USERNAME"),
+            test_reports: vars.required("CIRCLE_TEST_REPORTS"),
+            pr_username: vars.optional("CIRCLE_PR_USERNAME"),
+            pr_reponame: vars.optional("CIRCLE_PR_REPONAME"),
+            pr_number: vars.optional("CIRCLE_PR_NUMBER"),
+            node_total: vars.required("CIRCLE_NODE_TOTAL"),
+            node_index: vars.required("CIRCLE_NODE_INDEX"),
+            build_image: vars.optional("CIRCLE_BUILD_IMAGE"),
+            job: vars.optional("CIRCLE_JOB"),
+            workflow_job_id: vars.optional("CIRCLE_WORKFLOW_JOB_ID"),
+            missing: vars.missing(),
         })
     }
 }
+
+impl Provider for Circle {
+    fn name(&self) -> &str {
+        Circle::NAME
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.missing.given("CIRCLE_SHA1", &*self.sha1)
+    }
+
+    fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        let number = self.pr_number.map(|it| it as u64).or_else(|| {
+            let url = self.pull_request.as_ref()?;
+            url.rsplit('/').next()?.parse().ok()
+        })?;
+        let mut pr = PullRequest::new(number);
+        pr.url = self.pull_request.clone();
+        pr.source_branch = self.branch.clone();
+        pr.head_sha = self.commit_sha().map(str::to_owned);
+        if let (Some(username), Some(reponame)) = (&self.pr_username, &self.pr_reponame) {
+            pr.head_repo = Some(format!("{}/{}", username, reponame));
+            pr.is_fork = Some(true);
+        }
+        Some(pr)
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.missing
+            .given("CIRCLE_BUILD_NUM", self.build_num as u64)
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.missing.given("CIRCLE_BUILD_URL", &*self.build_url)
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        Some(format!(
+            "{}/{}",
+            self.missing
+                .given("CIRCLE_PROJECT_USERNAME", &self.project_username)?,
+            self.missing
+                .given("CIRCLE_PROJECT_REPONAME", &self.project_reponame)?
+        ))
+    }
+
+    fn repository(&self) -> Option<Repository> {
+        Repository::parse(&self.repository_url)
+            .or_else(|| Repository::from_slug(&self.repo_slug()?))
+    }
+
+    fn job(&self) -> Option<Job> {
+        let job = Job {
+            id: self.workflow_job_id.clone(),
+            name: self.job.clone(),
+            ..Job::default()
+        };
+        Some(job).filter(|job| *job != Job::default())
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        // CircleCI builds what is pushed to it; the environment doesn't say
+        // whether a pipeline was started another way, like on a schedule.
+        Trigger::of_push(&self.git_ref())
+    }
+
+    fn runner(&self) -> Runner {
+        let mut runner = Runner::new();
+        runner.image = self.build_image.clone();
+        runner.hosting = Some(Hosting::Hosted);
+        runner
+    }
+
+    fn parallelism(&self) -> Option<Parallelism> {
+        Some(Parallelism::new(
+            self.missing.given("CIRCLE_NODE_INDEX", self.node_index)?,
+            self.missing.given("CIRCLE_NODE_TOTAL", self.node_total)?,
+        ))
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        if !provenance::has_value(self, field) {
+            return None;
+        }
+        match field {
+            "commit_sha" => provenance::var("CIRCLE_SHA1"),
+            "branch" if self.branch.is_some() => provenance::var("CIRCLE_BRANCH"),
+            "tag" if self.tag.is_some() => provenance::var("CIRCLE_TAG"),
+            "pull_request" if self.pr_number.is_some() => provenance::var("CIRCLE_PR_NUMBER"),
+            "pull_request" if self.pull_request().is_some() => {
+                provenance::derived("CI_PULL_REQUEST")
+            }
+            "build_number" => provenance::var("CIRCLE_BUILD_NUM"),
+            "build_url" => provenance::var("CIRCLE_BUILD_URL"),
+            "repo_slug" => {
+                provenance::derived("CIRCLE_PROJECT_USERNAME and CIRCLE_PROJECT_REPONAME")
+            }
+            "repository" if Repository::parse(&self.repository_url).is_some() => {
+                provenance::derived("CIRCLE_REPOSITORY_URL")
+            }
+            "job" => provenance::derived("CIRCLE_JOB and CIRCLE_WORKFLOW_JOB_ID"),
+            "runner" if self.build_image.is_some() => {
+                provenance::derived("CIRCLECI and CIRCLE_BUILD_IMAGE")
+            }
+            "runner" => provenance::derived("CIRCLECI"),
+            "parallelism" => provenance::derived("CIRCLE_NODE_INDEX and CIRCLE_NODE_TOTAL"),
+            _ => provenance::default(self, field),
+        }
+    }
+}
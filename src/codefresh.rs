@@ -1,14 +1,22 @@
-use env;
+use build::{Build, Job};
+use commit::Commit;
+use provenance::{self, Provenance};
+use provider::Provider;
+use pull_request::PullRequest;
+use std::convert::Infallible;
 use std::path::PathBuf;
 use std::str::FromStr;
+use trigger::Trigger;
+use vars::{Detect, Missing, Placeholder, Vars};
 
 /// Codefresh CI
 ///
 /// # References
 ///
+/// - <https://codefresh.io/docs/docs/codefresh-yaml/variables/#system-provided-variables>
 /// - <https://github.com/codecov/codecov-bash/blob/8b76995ad4a95a61cecd4b049a448a402d91d197/codecov#L511-L520>
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[non_exhaustive]
 pub struct Codefresh {
     /// Repository owner.
     pub repo_owner: String,
@@ -41,52 +49,215 @@ pub struct Codefresh {
     pub build_url: String,
     /// Path to kubeconfig if exist
     pub kubeconfig_path: Option<PathBuf>,
-    non_exhaustive: (),
+    /// The person or tool that initiated the build.
+    pub build_initiator: Option<String>,
+    /// Name of the account the pipeline belongs to.
+    pub account: Option<String>,
+    /// Full name of the pipeline, including the project it belongs to.
+    pub pipeline_name: Option<String>,
+    /// Name of the step that is currently running.
+    pub step_name: Option<String>,
+    /// The number of the pull request, if the build was triggered by one.
+    pub pull_request_number: Option<u32>,
+    /// The target branch of the pull request, if the build was triggered by one.
+    pub pull_request_target: Option<String>,
+    /// The pull request action that triggered the build, e.g. `opened` or `synchronize`.
+    pub pull_request_action: Option<String>,
+    /// The labels of the pull request, if the build was triggered by one.
+    pub pull_request_labels: Vec<String>,
+    /// The git provider's id of the pull request, if the build was triggered by one.
+    pub pull_request_id: Option<String>,
+    /// The required variables that were missing or invalid, in lenient detection.
+    missing: Missing,
 }
 
-impl Codefresh {
-    /// Construct this provider's information from the environment.
-    pub fn from_env() -> Option<Self> {
+impl_from_env!(Codefresh);
+
+impl Detect for Codefresh {
+    const NAME: &'static str = "Codefresh";
+
+    fn read(vars: &mut Vars) -> Option<Self> {
+        if !vars.markers(&[("CF_BUILD_ID", None)]) {
+            return None;
+        }
+
         Some(Codefresh {
-            repo_owner: env("CF_REPO_OWNER")?,
-            repo_name: env("CF_REPO_NAME")?,
-            branch: env("CF_BRANCH")?,
-            commit_author: env("CF_COMMIT_AUTHOR")?,
-            commit_url: env("CF_COMMIT_URL")?,
-            commit_message: env("CF_COMMIT_MESSAGE")?,
-            revision: env("CF_REVISION")?,
-            short_revision: env("CF_SHORT_REVISION")?,
-            volume_name: env("CF_VOLUME_NAME")?,
-            volume_path: PathBuf::from(env("CF_VOLUME_PATH")?),
-            build_trigger: env("CF_BUILD_TRIGGER")?.parse().ok()?,
-            build_id: env("CF_BUILD_ID")?,
-            build_timestamp: env("CF_BUILD_TIMESTAMP")?,
-            build_url: env("CF_BUILD_URL")?,
-            kubeconfig_path: env("CF_KUBECONFIG_PATH").map(PathBuf::from),
-            non_exhaustive: (),
+            repo_owner: vars.required("CF_REPO_OWNER"),
+            repo_name: vars.required("CF_REPO_NAME"),
+            branch: vars.required("CF_BRANCH"),
+            commit_author: vars.required("CF_COMMIT_AUTHOR"),
+            commit_url: vars.required("CF_COMMIT_URL"),
+            commit_message: vars.required("CF_COMMIT_MESSAGE"),
+            revision: vars.required("CF_REVISION"),
+            short_revision: vars.required("CF_SHORT_REVISION"),
+            volume_name: vars.required("CF_VOLUME_NAME"),
+            volume_path: vars.required_path("CF_VOLUME_PATH"),
+            build_trigger: vars.required("CF_BUILD_TRIGGER"),
+            build_id: vars.required("CF_BUILD_ID"),
+            build_timestamp: vars.required("CF_BUILD_TIMESTAMP"),
+            build_url: vars.required("CF_BUILD_URL"),
+            kubeconfig_path: vars.optional_path("CF_KUBECONFIG_PATH"),
+            build_initiator: vars.optional("CF_BUILD_INITIATOR"),
+            account: vars.optional("CF_ACCOUNT"),
+            pipeline_name: vars.optional("CF_PIPELINE_NAME"),
+            step_name: vars.optional("CF_STEP_NAME"),
+            pull_request_number: vars.optional("CF_PULL_REQUEST_NUMBER"),
+            pull_request_target: vars.optional("CF_PULL_REQUEST_TARGET"),
+            pull_request_action: vars.optional("CF_PULL_REQUEST_ACTION"),
+            pull_request_labels: vars
+                .optional("CF_PULL_REQUEST_LABELS")
+                .map(|labels: String| {
+                    labels
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|label| !label.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            pull_request_id: vars.optional("CF_PULL_REQUEST_ID"),
+            missing: vars.missing(),
+        })
+    }
+}
+
+impl Provider for Codefresh {
+    fn name(&self) -> &str {
+        Codefresh::NAME
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.missing.given("CF_REVISION", &*self.revision)
+    }
+
+    fn commit(&self) -> Option<Commit> {
+        let mut commit = Commit::new(self.commit_sha()?);
+        if let Some(short_sha) = self
+            .missing
+            .given("CF_SHORT_REVISION", &self.short_revision)
+        {
+            commit.short_sha = short_sha.clone();
+        }
+        commit.set_message(&self.commit_message);
+        commit.author_name = self
+            .missing
+            .given("CF_COMMIT_AUTHOR", self.commit_author.clone());
+        commit.url = self.missing.given("CF_COMMIT_URL", self.commit_url.clone());
+        Some(commit)
+    }
+
+    fn branch(&self) -> Option<&str> {
+        self.missing.given("CF_BRANCH", &*self.branch)
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        let mut pr = PullRequest::new(self.pull_request_number?.into());
+        pr.source_branch = self.branch().map(str::to_owned);
+        pr.target_branch = self.pull_request_target.clone();
+        pr.head_sha = self.commit_sha().map(str::to_owned);
+        Some(pr)
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.missing.given("CF_BUILD_URL", &*self.build_url)
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        Some(format!(
+            "{}/{}",
+            self.missing.given("CF_REPO_OWNER", &self.repo_owner)?,
+            self.missing.given("CF_REPO_NAME", &self.repo_name)?
+        ))
+    }
+
+    fn build(&self) -> Build {
+        Build {
+            id: self.missing.given("CF_BUILD_ID", self.build_id.clone()),
+            url: self.build_url().map(str::to_owned),
+            ..Build::default()
+        }
+    }
+
+    fn job(&self) -> Option<Job> {
+        Some(Job {
+            name: Some(self.step_name.clone()?),
+            ..Job::default()
         })
     }
+
+    fn trigger(&self) -> Option<Trigger> {
+        match *self
+            .missing
+            .given("CF_BUILD_TRIGGER", &self.build_trigger)?
+        {
+            BuildTrigger::Build | BuildTrigger::Cli => Some(Trigger::Manual),
+            BuildTrigger::Webhook if self.pull_request_number.is_some() => {
+                Some(Trigger::PullRequest)
+            }
+            BuildTrigger::Webhook => Some(Trigger::Push),
+            BuildTrigger::Cron => Some(Trigger::Schedule),
+            BuildTrigger::Api => Some(Trigger::Api),
+            BuildTrigger::Other(_) => None,
+        }
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        if !provenance::has_value(self, field) {
+            return None;
+        }
+        match field {
+            "commit_sha" => provenance::var("CF_REVISION"),
+            "commit" => provenance::derived(
+                "CF_REVISION, CF_SHORT_REVISION, CF_COMMIT_MESSAGE, CF_COMMIT_AUTHOR and CF_COMMIT_URL",
+            ),
+            "branch" => provenance::var("CF_BRANCH"),
+            "pull_request" if self.pull_request_number.is_some() => {
+                provenance::var("CF_PULL_REQUEST_NUMBER")
+            }
+            "trigger" if self.trigger().is_some() => provenance::var("CF_BUILD_TRIGGER"),
+            "build_url" => provenance::var("CF_BUILD_URL"),
+            "build" => provenance::derived("CF_BUILD_ID and CF_BUILD_URL"),
+            "repo_slug" => provenance::derived("CF_REPO_OWNER and CF_REPO_NAME"),
+            "job" if self.step_name.is_some() => provenance::var("CF_STEP_NAME"),
+            _ => provenance::default(self, field),
+        }
+    }
 }
 
 /// How the current build was triggered.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
 pub enum BuildTrigger {
     /// The build was triggered from the build button.
     Build,
     /// The build was triggered from a control version webhook.
     Webhook,
-    #[doc(hidden)]
-    __NonExhaustive,
+    /// The build was triggered by a cron trigger.
+    Cron,
+    /// The build was triggered from the Codefresh CLI.
+    Cli,
+    /// The build was triggered through the Codefresh API.
+    Api,
+    /// The build was triggered in a way this crate does not know about yet.
+    Other(String),
 }
 
 impl FromStr for BuildTrigger {
-    type Err = ();
+    type Err = Infallible;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "build" => Ok(BuildTrigger::Build),
-            "webhook" => Ok(BuildTrigger::Webhook),
-            _ => Err(()),
-        }
+        Ok(match s {
+            "build" => BuildTrigger::Build,
+            "webhook" => BuildTrigger::Webhook,
+            "cron" => BuildTrigger::Cron,
+            "cli" => BuildTrigger::Cli,
+            "api" => BuildTrigger::Api,
+            _ => BuildTrigger::Other(s.to_owned()),
+        })
+    }
+}
+
+impl Placeholder for BuildTrigger {
+    fn placeholder() -> Self {
+        BuildTrigger::Other(String::new())
     }
 }
@@ -1,45 +1,202 @@
-use env;
+use build::Build;
+use commit::Commit;
+use provenance::{self, Provenance};
+use provider::Provider;
+use pull_request::PullRequest;
+use runner::{Hosting, Runner};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use trigger::Trigger;
+use vars::{Detect, Missing, Vars};
 
 /// Codeship CI
 ///
 /// # References
 ///
 /// - <https://documentation.codeship.com/basic/builds-and-configuration/set-environment-variables/#default-environment-variables>
+/// - <https://documentation.codeship.com/pro/builds-and-configuration/environment-variables/#default-environment-variables>
 /// - <https://github.com/codecov/codecov-bash/blob/8b76995ad4a95a61cecd4b049a448a402d91d197/codecov#L501-L510>
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[non_exhaustive]
 #[allow(missing_docs)]
 pub struct Codeship {
+    /// Whether this is a Codeship Basic or Codeship Pro build.
+    pub flavor: Flavor,
     pub branch: String,
-    pub build_number: String,
-    pub build_url: String,
+    /// (Codeship Basic only)
+    pub build_number: Option<String>,
+    /// (Codeship Basic only)
+    pub build_url: Option<String>,
     pub committer_email: String,
     pub committer_name: String,
-    pub committer_username: String,
+    /// (Codeship Basic only, unless exported by the Pro configuration)
+    pub committer_username: Option<String>,
     pub commit_id: String,
     pub message: String,
-    pub repo_name: String,
-    non_exhaustive: (),
+    /// (Codeship Basic only, unless exported by the Pro configuration)
+    pub repo_name: Option<String>,
+    /// (Codeship Pro only)
+    pub build_id: Option<String>,
+    /// (Codeship Pro only)
+    pub pr_number: Option<u32>,
+    /// (Codeship Pro only)
+    pub pull_request: Option<String>,
+    /// The time the build was started, as a human readable string.
+    /// (Codeship Pro only)
+    pub string_time: Option<String>,
+    /// The time the build was started.
+    /// (Codeship Pro only)
+    pub timestamp: Option<SystemTime>,
+    /// The required variables that were missing or invalid, in lenient detection.
+    missing: Missing,
 }
 
-impl Codeship {
-    /// Construct this provider's information from the environment.
-    pub fn from_env() -> Option<Self> {
-        if !(env("CI")? == "true" && env("CI_NAME")? == "codeship") {
+impl_from_env!(Codeship);
+
+impl Detect for Codeship {
+    const NAME: &'static str = "Codeship";
+
+    fn read(vars: &mut Vars) -> Option<Self> {
+        if !vars.markers(&[("CI", Some("true")), ("CI_NAME", Some("codeship"))]) {
             return None;
         }
 
+        let flavor = if vars.is_set("CI_BUILD_NUMBER") {
+            Flavor::Basic
+        } else {
+            Flavor::Pro
+        };
+        let basic = flavor == Flavor::Basic;
         Some(Codeship {
-            branch: env("CI_BRANCH")?,
-            build_number: env("CI_BUILD_NUMBER")?,
-            build_url: env("CI_BUILD_URL")?,
-            committer_email: env("CI_COMMITTER_EMAIL")?,
-            committer_name: env("CI_COMMITTER_NAME")?,
-            committer_username: env("CI_COMMITTER_USERNAME")?,
-            commit_id: env("CI_COMMIT_ID")?,
-            message: env("CI_MESSAGE")?,
-            repo_name: env("CI_REPO_NAME")?,
-            non_exhaustive: (),
+            flavor,
+            branch: vars.required("CI_BRANCH"),
+            build_number: vars.optional("CI_BUILD_NUMBER"),
+            build_url: vars.required_if(basic, "CI_BUILD_URL"),
+            committer_email: vars.required("CI_COMMITTER_EMAIL"),
+            committer_name: vars.required("CI_COMMITTER_NAME"),
+            committer_username: vars.required_if(basic, "CI_COMMITTER_USERNAME"),
+            commit_id: vars.required("CI_COMMIT_ID"),
+            message: vars.required(if basic {
+                "CI_MESSAGE"
+            } else {
+                "CI_COMMIT_MESSAGE"
+            }),
+            repo_name: vars.required_if(basic, "CI_REPO_NAME"),
+            build_id: vars.optional("CI_BUILD_ID"),
+            pr_number: vars.optional("CI_PR_NUMBER"),
+            pull_request: vars.optional("CI_PULL_REQUEST").filter(|pr| pr != "false"),
+            string_time: vars.optional("CI_STRING_TIME"),
+            timestamp: vars
+                .optional("CI_TIMESTAMP")
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            missing: vars.missing(),
         })
     }
 }
+
+impl Provider for Codeship {
+    fn name(&self) -> &str {
+        Codeship::NAME
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.missing.given("CI_COMMIT_ID", &*self.commit_id)
+    }
+
+    fn commit(&self) -> Option<Commit> {
+        let mut commit = Commit::new(self.commit_sha()?);
+        commit.set_message(&self.message);
+        // Codeship only knows the committer, which is usually also the author.
+        commit.author_name = self
+            .missing
+            .given("CI_COMMITTER_NAME", self.committer_name.clone());
+        commit.author_email = self
+            .missing
+            .given("CI_COMMITTER_EMAIL", self.committer_email.clone());
+        Some(commit)
+    }
+
+    fn branch(&self) -> Option<&str> {
+        self.missing.given("CI_BRANCH", &*self.branch)
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        // Codeship Pro sets `CI_PR_NUMBER=0` and `CI_PULL_REQUEST=false` on other builds.
+        let number = self.pr_number.filter(|&number| number != 0)?;
+        let pull_request = self.pull_request.as_ref()?;
+        let mut pr = PullRequest::new(number.into());
+        if pull_request.starts_with("http") {
+            pr.url = Some(pull_request.clone());
+        }
+        pr.source_branch = self.branch().map(str::to_owned);
+        pr.head_sha = self.commit_sha().map(str::to_owned);
+        Some(pr)
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.build_number.as_ref()?.parse().ok()
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.build_url.as_deref()
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        self.repo_name.clone()
+    }
+
+    fn build(&self) -> Build {
+        Build {
+            id: self.build_id.clone(),
+            number: self.build_number(),
+            url: self.build_url.clone(),
+            ..Build::default()
+        }
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        // Codeship only builds what is pushed to it, and pull requests on Codeship Pro.
+        Trigger::of_push(&self.git_ref())
+    }
+
+    fn runner(&self) -> Runner {
+        let mut runner = Runner::new();
+        runner.hosting = Some(Hosting::Hosted);
+        runner
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        if !provenance::has_value(self, field) {
+            return None;
+        }
+        match field {
+            "commit_sha" => provenance::var("CI_COMMIT_ID"),
+            "commit" => provenance::derived(match self.flavor {
+                Flavor::Basic => {
+                    "CI_COMMIT_ID, CI_MESSAGE, CI_COMMITTER_NAME and CI_COMMITTER_EMAIL"
+                }
+                Flavor::Pro => {
+                    "CI_COMMIT_ID, CI_COMMIT_MESSAGE, CI_COMMITTER_NAME and CI_COMMITTER_EMAIL"
+                }
+            }),
+            "branch" => provenance::var("CI_BRANCH"),
+            "pull_request" if self.pull_request().is_some() => {
+                provenance::derived("CI_PR_NUMBER and CI_PULL_REQUEST")
+            }
+            "build_number" if self.build_number().is_some() => provenance::var("CI_BUILD_NUMBER"),
+            "build_url" if self.build_url.is_some() => provenance::var("CI_BUILD_URL"),
+            "repo_slug" if self.repo_name.is_some() => provenance::var("CI_REPO_NAME"),
+            "runner" => provenance::derived("CI_NAME"),
+            _ => provenance::default(self, field),
+        }
+    }
+}
+
+/// Which of Codeship's products is running the build.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Flavor {
+    /// Codeship Basic, running the build directly on a build VM.
+    Basic,
+    /// Codeship Pro, running the build in Docker containers.
+    Pro,
+}
@@ -0,0 +1,83 @@
+/// The commit being built, normalized across providers.
+///
+/// Providers fill in as much as they know; everything but the SHA is optional.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Commit {
+    /// The full SHA of the commit.
+    pub sha: String,
+    /// The abbreviated SHA of the commit, as shown by git.
+    pub short_sha: String,
+    /// The first line of the commit message.
+    pub subject: Option<String>,
+    /// The rest of the commit message, without the blank line separating it from the subject.
+    pub body: Option<String>,
+    /// The name of the author of the commit.
+    pub author_name: Option<String>,
+    /// The email address of the author of the commit.
+    pub author_email: Option<String>,
+    /// When the commit was authored, as reported by the provider (usually RFC 3339).
+    pub timestamp: Option<String>,
+    /// A link to the commit.
+    pub url: Option<String>,
+}
+
+impl Commit {
+    /// A commit of which only the SHA is known.
+    pub fn new(sha: &str) -> Self {
+        Commit {
+            sha: sha.to_owned(),
+            short_sha: sha.chars().take(7).collect(),
+            subject: None,
+            body: None,
+            author_name: None,
+            author_email: None,
+            timestamp: None,
+            url: None,
+        }
+    }
+
+    /// Split a full commit message into `subject` and `body`.
+    pub fn set_message(&mut self, message: &str) {
+        let mut lines = message.trim().splitn(2, '\n');
+        self.subject = lines
+            .next()
+            .map(str::trim_end)
+            .filter(|it| !it.is_empty())
+            .map(String::from);
+        self.body = lines
+            .next()
+            .map(str::trim)
+            .filter(|it| !it.is_empty())
+            .map(String::from);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(message: &str) -> (Option<String>, Option<String>) {
+        let mut commit = Commit::new("0123456789abcdef");
+        commit.set_message(message);
+        (commit.subject, commit.body)
+    }
+
+    #[test]
+    fn set_message() {
+        assert_eq!(message("Fix it"), (Some("Fix it".to_owned()), None));
+        assert_eq!(
+            message("Fix it\n\nIt was broken.\nReally.\n"),
+            (
+                Some("Fix it".to_owned()),
+                Some("It was broken.\nReally.".to_owned())
+            )
+        );
+        assert_eq!(
+            message("  Fix it  \r\n\r\nBody\r\n"),
+            (Some("Fix it".to_owned()), Some("Body".to_owned()))
+        );
+        assert_eq!(message(""), (None, None));
+        assert_eq!(message(" \n \n"), (None, None));
+    }
+}
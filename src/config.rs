@@ -0,0 +1,640 @@
+use error::{InvalidVar, Partial, Warning};
+use provenance::{self, Provenance};
+use provider::Provider;
+use pull_request::PullRequest;
+use regex::Regex;
+use repository::Repository;
+use source::{self, EnvSource, ProcessEnv};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, io};
+use toml::{Table, Value};
+
+/// A provider declared in a `ci-detective.toml` file, for CIs that don't need Rust code.
+///
+/// The file names the provider, lists its marker variables, and maps variables onto
+/// normalized fields. A mapping is either the name of a variable, or a table with the
+/// variable in `var`, a prefix to remove in `strip_prefix`, and a `regex` whose first
+/// capture group (or whole match, if it has none) is the value.
+/// A field is unset if its variable is unset or the regex doesn't match.
+///
+/// ```toml
+/// name = "Build Farm"
+/// markers = ["FARM_BUILD_ID", { var = "FARM", value = "true" }]
+///
+/// [fields]
+/// commit = "FARM_SHA"
+/// branch = { var = "FARM_REF", strip_prefix = "refs/heads/" }
+/// pull_request = { var = "FARM_REF", regex = '^refs/pull/(\d+)/' }
+/// build_url = "FARM_BUILD_URL"
+/// ```
+///
+/// The fields are `commit`, `branch`, `tag`, `pull_request`, `build_number`, `build_url`,
+/// `repo_slug`, `repository_url` and `job_id`.
+#[derive(Clone, Debug)]
+pub struct Config {
+    name: String,
+    markers: Vec<(String, Option<String>)>,
+    fields: Vec<(Field, Mapping)>,
+    path: Option<PathBuf>,
+}
+
+impl Config {
+    /// The file read if `CI_DETECTIVE_CONFIG` is not set, relative to the current directory.
+    pub const FILE_NAME: &'static str = "ci-detective.toml";
+
+    /// Load the config of this process: the file `CI_DETECTIVE_CONFIG` points to,
+    /// or `ci-detective.toml` if it exists.
+    pub fn load() -> Result<Option<Self>, ConfigError> {
+        Config::load_from(&ProcessEnv)
+    }
+
+    /// Like [`load`](Config::load), but reading `CI_DETECTIVE_CONFIG` from the given variables.
+    pub fn load_from<S: EnvSource + ?Sized>(source: &S) -> Result<Option<Self>, ConfigError> {
+        // An empty `CI_DETECTIVE_CONFIG` is unset, like every other variable.
+        let path = match source
+            .var_os("CI_DETECTIVE_CONFIG")
+            .filter(|it| !it.is_empty())
+        {
+            Some(path) => PathBuf::from(path),
+            None if Path::new(Config::FILE_NAME).is_file() => PathBuf::from(Config::FILE_NAME),
+            None => return Ok(None),
+        };
+        Config::read(&path).map(Some)
+    }
+
+    /// Read a config file.
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_owned(), err))?;
+        let mut config = Config::parse(&text)?;
+        config.path = Some(path.to_owned());
+        Ok(config)
+    }
+
+    /// Parse the contents of a config file.
+    ///
+    /// Fields of a config that was not [`read`](Config::read) from a file have
+    /// the provenance of their variables.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let table: Table = text.parse().map_err(ConfigError::Toml)?;
+        let mut config = Config {
+            name: String::new(),
+            markers: Vec::new(),
+            fields: Vec::new(),
+            path: None,
+        };
+        for (key, value) in &table {
+            match key.as_str() {
+                "name" => config.name = string(value, "name")?,
+                "markers" => config.markers = markers(value)?,
+                "fields" => config.fields = fields(value)?,
+                _ => return Err(invalid(format!("unknown key `{}`", key))),
+            }
+        }
+        if config.name.is_empty() {
+            return Err(invalid("`name` is missing".to_owned()));
+        }
+        if config.markers.is_empty() {
+            return Err(invalid("`markers` is missing or empty".to_owned()));
+        }
+        Ok(config)
+    }
+
+    /// The name of the declared provider.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Read the declared provider from the given variables, if its markers are found.
+    pub fn detect<S: EnvSource + ?Sized>(&self, source: &S) -> Option<Declared> {
+        self.detect_lenient(source).map(|partial| partial.provider)
+    }
+
+    /// Like [`detect`](Config::detect), listing the fields' variables that could not be read,
+    /// or that did not parse or match, as warnings.
+    ///
+    /// All fields are optional, so the declared provider is detected whenever its markers are.
+    pub fn detect_lenient<S: EnvSource + ?Sized>(&self, source: &S) -> Option<Partial<Declared>> {
+        let source = &source::ByRef(source);
+        for (name, expected) in &self.markers {
+            // Without an expected value, any value will do, even one that is not valid Unicode.
+            let value = source.var_os(name).filter(|value| !value.is_empty())?;
+            match *expected {
+                Some(ref expected) if value.to_str() != Some(expected) => return None,
+                _ => {}
+            }
+        }
+
+        let mut declared = Declared {
+            name: self.name.clone(),
+            commit: None,
+            branch: None,
+            tag: None,
+            pull_request: None,
+            build_number: None,
+            build_url: None,
+            repo_slug: None,
+            repository_url: None,
+            job_id: None,
+            sources: Vec::new(),
+        };
+        let mut warnings = Vec::new();
+        for &(field, ref mapping) in &self.fields {
+            let value = match source::var(source, &mapping.var, &mut warnings) {
+                Some(value) => value,
+                None => continue,
+            };
+            let invalid = |expected: &str| {
+                Warning::Invalid(InvalidVar {
+                    name: mapping.var.clone(),
+                    value: value.clone(),
+                    expected: expected.to_owned(),
+                })
+            };
+            let mapped = match mapping.apply(&value) {
+                Some(mapped) => mapped,
+                None => {
+                    warnings.push(invalid(&mapping.expected()));
+                    continue;
+                }
+            };
+            match field {
+                Field::Commit => declared.commit = Some(mapped),
+                Field::Branch => declared.branch = Some(mapped),
+                Field::Tag => declared.tag = Some(mapped),
+                Field::PullRequest => match mapped.parse() {
+                    Ok(number) => declared.pull_request = Some(number),
+                    Err(_) => {
+                        warnings.push(invalid("a pull request number"));
+                        continue;
+                    }
+                },
+                Field::BuildNumber => match mapped.parse() {
+                    Ok(number) => declared.build_number = Some(number),
+                    Err(_) => {
+                        warnings.push(invalid("u64"));
+                        continue;
+                    }
+                },
+                Field::BuildUrl => declared.build_url = Some(mapped),
+                Field::RepoSlug => declared.repo_slug = Some(mapped),
+                Field::RepositoryUrl => declared.repository_url = Some(mapped),
+                Field::JobId => declared.job_id = Some(mapped),
+            }
+            declared
+                .sources
+                .push((field, mapping.provenance(self.path.as_deref())));
+        }
+        Some(Partial {
+            provider: declared,
+            confidence: 1.0,
+            warnings,
+        })
+    }
+}
+
+/// A provider declared in a [`Config`], as read from the environment.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Declared {
+    /// The name of the provider.
+    pub name: String,
+    /// The SHA of the commit being built.
+    pub commit: Option<String>,
+    /// The branch being built.
+    pub branch: Option<String>,
+    /// The tag being built.
+    pub tag: Option<String>,
+    /// The number of the pull request being built.
+    pub pull_request: Option<u64>,
+    /// The number of the build.
+    pub build_number: Option<u64>,
+    /// A link to the build.
+    pub build_url: Option<String>,
+    /// The slug of the repository, in the form `owner/name`.
+    pub repo_slug: Option<String>,
+    /// The clone or web URL of the repository.
+    pub repository_url: Option<String>,
+    /// The id of the job within the build.
+    pub job_id: Option<String>,
+    sources: Vec<(Field, Provenance)>,
+}
+
+impl Provider for Declared {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+
+    fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        let mut pr = PullRequest::new(self.pull_request?);
+        pr.source_branch = self.branch.clone();
+        Some(pr)
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.build_number
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.build_url.as_deref()
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        self.repo_slug.clone()
+    }
+
+    fn repository(&self) -> Option<Repository> {
+        match self.repository_url {
+            Some(ref url) => Repository::parse(url),
+            None => Repository::from_slug(self.repo_slug.as_ref()?),
+        }
+    }
+
+    fn job_id(&self) -> Option<&str> {
+        self.job_id.as_deref()
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        if !provenance::has_value(self, field) {
+            return None;
+        }
+        let key = match field {
+            "commit_sha" => Field::Commit,
+            "repository" if self.repository_url.is_some() => Field::RepositoryUrl,
+            field => match Field::from_key(field) {
+                Some(key) => key,
+                None => return provenance::default(self, field),
+            },
+        };
+        self.sources
+            .iter()
+            .rev()
+            .find(|it| it.0 == key)
+            .map(|it| it.1.clone())
+    }
+}
+
+/// Why a config file could not be loaded.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The file could not be read.
+    Io(PathBuf, io::Error),
+    /// The file is not valid TOML.
+    Toml(toml::de::Error),
+    /// The file is valid TOML, but not a valid config.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+            ConfigError::Toml(ref err) => err.fmt(f),
+            ConfigError::Invalid(ref message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ConfigError::Io(_, ref err) => Some(err),
+            ConfigError::Toml(ref err) => Some(err),
+            ConfigError::Invalid(_) => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Field {
+    Commit,
+    Branch,
+    Tag,
+    PullRequest,
+    BuildNumber,
+    BuildUrl,
+    RepoSlug,
+    RepositoryUrl,
+    JobId,
+}
+
+impl Field {
+    fn from_key(key: &str) -> Option<Self> {
+        Some(match key {
+            "commit" => Field::Commit,
+            "branch" => Field::Branch,
+            "tag" => Field::Tag,
+            "pull_request" => Field::PullRequest,
+            "build_number" => Field::BuildNumber,
+            "build_url" => Field::BuildUrl,
+            "repo_slug" => Field::RepoSlug,
+            "repository_url" => Field::RepositoryUrl,
+            "job_id" => Field::JobId,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Mapping {
+    var: String,
+    strip_prefix: Option<String>,
+    regex: Option<Regex>,
+}
+
+impl Mapping {
+    fn provenance(&self, path: Option<&Path>) -> Provenance {
+        match (path, &self.strip_prefix, &self.regex) {
+            (Some(path), _, _) => Provenance::File(path.to_owned()),
+            (None, None, None) => Provenance::Var(self.var.clone()),
+            (None, _, _) => Provenance::Derived(self.var.clone()),
+        }
+    }
+
+    /// What [`apply`](Mapping::apply) needs to find in the variable.
+    fn expected(&self) -> String {
+        match self.regex {
+            Some(ref regex) => format!("a match for {:?}", regex.as_str()),
+            None => "more than its prefix".to_owned(),
+        }
+    }
+
+    fn apply(&self, value: &str) -> Option<String> {
+        let value = match self.strip_prefix {
+            Some(ref prefix) => value.strip_prefix(prefix.as_str()).unwrap_or(value),
+            None => value,
+        };
+        let value = match self.regex {
+            Some(ref regex) => {
+                let captures = regex.captures(value)?;
+                captures.get(1).or_else(|| captures.get(0))?.as_str()
+            }
+            None => value,
+        };
+        Some(value.to_owned()).filter(|it| !it.is_empty())
+    }
+}
+
+fn invalid(message: String) -> ConfigError {
+    ConfigError::Invalid(message)
+}
+
+fn string(value: &Value, what: &str) -> Result<String, ConfigError> {
+    value
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| invalid(format!("`{}` must be a string", what)))
+}
+
+fn markers(value: &Value) -> Result<Vec<(String, Option<String>)>, ConfigError> {
+    let markers = value
+        .as_array()
+        .ok_or_else(|| invalid("`markers` must be an array".to_owned()))?;
+    markers
+        .iter()
+        .map(|marker| match *marker {
+            Value::String(ref name) => Ok((name.clone(), None)),
+            Value::Table(ref table) => {
+                let mut var = None;
+                let mut expected = None;
+                for (key, value) in table {
+                    match key.as_str() {
+                        "var" => var = Some(string(value, "markers.var")?),
+                        "value" => expected = Some(string(value, "markers.value")?),
+                        _ => return Err(invalid(format!("unknown key `markers.{}`", key))),
+                    }
+                }
+                let var = var.ok_or_else(|| invalid("a marker is missing `var`".to_owned()))?;
+                Ok((var, expected))
+            }
+            _ => Err(invalid(
+                "a marker must be a variable name or a table".to_owned(),
+            )),
+        })
+        .collect()
+}
+
+fn fields(value: &Value) -> Result<Vec<(Field, Mapping)>, ConfigError> {
+    let fields = value
+        .as_table()
+        .ok_or_else(|| invalid("`fields` must be a table".to_owned()))?;
+    fields
+        .iter()
+        .map(|(key, value)| {
+            let field =
+                Field::from_key(key).ok_or_else(|| invalid(format!("unknown field `{}`", key)))?;
+            Ok((field, mapping(key, value)?))
+        })
+        .collect()
+}
+
+fn mapping(field: &str, value: &Value) -> Result<Mapping, ConfigError> {
+    let mut mapping = Mapping {
+        var: String::new(),
+        strip_prefix: None,
+        regex: None,
+    };
+    match *value {
+        Value::String(ref var) => mapping.var = var.clone(),
+        Value::Table(ref table) => {
+            for (key, value) in table {
+                let what = format!("fields.{}.{}", field, key);
+                match key.as_str() {
+                    "var" => mapping.var = string(value, &what)?,
+                    "strip_prefix" => mapping.strip_prefix = Some(string(value, &what)?),
+                    "regex" => {
+                        let regex = Regex::new(&string(value, &what)?)
+                            .map_err(|err| invalid(format!("`{}`: {}", what, err)))?;
+                        mapping.regex = Some(regex);
+                    }
+                    _ => return Err(invalid(format!("unknown key `{}`", what))),
+                }
+            }
+        }
+        _ => {
+            return Err(invalid(format!(
+                "`fields.{}` must be a variable name or a table",
+                field
+            )))
+        }
+    }
+    if mapping.var.is_empty() {
+        return Err(invalid(format!("`fields.{}` is missing `var`", field)));
+    }
+    Ok(mapping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FARM: &str = r#"
+        name = "Build Farm"
+        markers = ["FARM_BUILD_ID", { var = "FARM", value = "true" }]
+
+        [fields]
+        commit = "FARM_SHA"
+        branch = { var = "FARM_REF", strip_prefix = "refs/heads/" }
+        pull_request = { var = "FARM_REF", regex = '^refs/pull/(\d+)/' }
+        build_number = "FARM_BUILD_NUMBER"
+    "#;
+
+    #[test]
+    fn parse() {
+        let config = Config::parse(FARM).unwrap();
+        assert_eq!(config.name(), "Build Farm");
+        assert_eq!(
+            config.markers,
+            [
+                ("FARM_BUILD_ID".to_owned(), None),
+                ("FARM".to_owned(), Some("true".to_owned())),
+            ]
+        );
+        assert_eq!(config.fields.len(), 4);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("markers = [\"X\"]", "`name` is missing"),
+            ("name = \"X\"", "`markers` is missing or empty"),
+            (
+                "name = \"X\"\nmarkers = []",
+                "`markers` is missing or empty",
+            ),
+            ("name = 1\nmarkers = [\"X\"]", "`name` must be a string"),
+            (
+                "name = \"X\"\nmarkers = [\"X\"]\nkind = 1",
+                "unknown key `kind`",
+            ),
+            (
+                "name = \"X\"\nmarkers = [{ value = \"1\" }]",
+                "a marker is missing `var`",
+            ),
+            (
+                "name = \"X\"\nmarkers = [\"X\"]\n[fields]\nauthor = \"A\"",
+                "unknown field `author`",
+            ),
+            (
+                "name = \"X\"\nmarkers = [\"X\"]\n[fields]\nbranch = { strip_prefix = \"x\" }",
+                "`fields.branch` is missing `var`",
+            ),
+        ];
+        for &(text, message) in &cases {
+            match Config::parse(text) {
+                Err(ConfigError::Invalid(ref err)) => assert_eq!(err, message, "{}", text),
+                other => panic!("{}: {:?}", text, other),
+            }
+        }
+        assert!(matches!(
+            Config::parse("name = "),
+            Err(ConfigError::Toml(_))
+        ));
+        assert!(matches!(
+            Config::parse("name = \"X\"\nmarkers = [\"X\"]\n[fields]\nbranch = { var = \"B\", regex = \"(\" }"),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn detect() {
+        let config = Config::parse(FARM).unwrap();
+        assert!(config.detect(&[("FARM_BUILD_ID", "1")]).is_none());
+        assert!(config
+            .detect(&[("FARM_BUILD_ID", "1"), ("FARM", "false")])
+            .is_none());
+
+        let env = [
+            ("FARM_BUILD_ID", "1"),
+            ("FARM", "true"),
+            ("FARM_REF", "refs/pull/12/head"),
+        ];
+        let declared = config.detect(&env).unwrap();
+        assert_eq!(declared.pull_request, Some(12));
+        assert_eq!(declared.branch.as_deref(), Some("refs/pull/12/head"));
+        assert_eq!(declared.commit_sha(), None);
+        assert_eq!(
+            declared.provenance("pull_request"),
+            Some(Provenance::Derived("FARM_REF".to_owned()))
+        );
+
+        let config = Config {
+            path: Some(PathBuf::from("farm.toml")),
+            ..config
+        };
+        let declared = config.detect(&env).unwrap();
+        assert_eq!(
+            declared.provenance("pull_request"),
+            Some(Provenance::File(PathBuf::from("farm.toml")))
+        );
+    }
+
+    #[test]
+    fn detect_reports_invalid_fields() {
+        let config = Config::parse(FARM).unwrap();
+        let env = [
+            ("FARM_BUILD_ID", "1"),
+            ("FARM", "true"),
+            ("FARM_REF", "refs/heads/main"),
+            ("FARM_BUILD_NUMBER", "not a number"),
+        ];
+        let partial = config.detect_lenient(&env).unwrap();
+        assert_eq!(partial.provider.branch(), Some("main"));
+        assert_eq!(partial.provider.pull_request, None);
+        assert_eq!(partial.provider.build_number(), None);
+        assert_eq!(partial.provider.provenance("build_number"), None);
+        let warnings: Vec<_> = partial.warnings.iter().map(|it| it.to_string()).collect();
+        assert_eq!(
+            warnings,
+            [
+                "FARM_BUILD_NUMBER=\"not a number\" is not u64",
+                "FARM_REF=\"refs/heads/main\" is not a match for \"^refs/pull/(\\\\d+)/\"",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detect_reports_non_unicode_fields() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let config = Config::parse(FARM).unwrap();
+        let env = [
+            ("FARM_BUILD_ID", OsStr::from_bytes(b"\xff")),
+            ("FARM", OsStr::new("true")),
+            ("FARM_SHA", OsStr::from_bytes(b"caf\xe9")),
+        ];
+        let partial = config.detect_lenient(&env).unwrap();
+        assert_eq!(partial.provider.commit_sha(), None);
+        assert!(matches!(
+            partial.warnings[..],
+            [Warning::NotUnicode(ref name)] if name == "FARM_SHA"
+        ));
+    }
+
+    #[test]
+    fn empty_path_is_unset() {
+        // There is no `ci-detective.toml` next to the manifest, where tests run.
+        assert!(Config::load_from(&[("CI_DETECTIVE_CONFIG", "")])
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            Config::load_from(&[("CI_DETECTIVE_CONFIG", "no/such/ci-detective.toml")]),
+            Err(ConfigError::Io(..))
+        ));
+    }
+}
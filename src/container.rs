@@ -0,0 +1,193 @@
+use std::fs;
+use std::path::Path;
+
+/// The container runtime this process is running in.
+///
+/// This is independent of the CI provider: most providers run their jobs in a container,
+/// and a container can be used without any CI at all.
+///
+/// # References
+///
+/// - <https://docs.docker.com/engine/reference/run/>
+/// - <https://docs.podman.io/en/latest/markdown/podman-run.1.html>
+/// - <https://man7.org/linux/man-pages/man7/cgroups.7.html>
+/// - <https://man7.org/linux/man-pages/man5/proc.5.html>
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Container {
+    /// Docker, which creates `/.dockerenv`.
+    Docker,
+    /// Podman, which creates `/run/.containerenv`.
+    Podman,
+    /// containerd, usually driven by Kubernetes or `nerdctl`.
+    Containerd,
+    /// LXC or LXD.
+    Lxc,
+}
+
+impl Container {
+    /// Detect the container runtime this process is running in, if any.
+    ///
+    /// The marker files `/.dockerenv` and `/run/.containerenv` are checked first,
+    /// then the cgroup of the init process (`/proc/1/cgroup`),
+    /// then what is mounted at `/` for this process (`/proc/self/mountinfo`).
+    pub fn detect() -> Option<Self> {
+        if Path::new("/run/.containerenv").exists() {
+            return Some(Container::Podman);
+        }
+        if Path::new("/.dockerenv").exists() {
+            return Some(Container::Docker);
+        }
+
+        let cgroup = fs::read_to_string("/proc/1/cgroup").unwrap_or_default();
+        if let Some(container) = cgroup.lines().filter_map(Container::from_cgroup).next() {
+            return Some(container);
+        }
+
+        let mountinfo = fs::read_to_string("/proc/self/mountinfo").unwrap_or_default();
+        mountinfo
+            .lines()
+            .filter_map(Container::from_mountinfo)
+            .next()
+    }
+
+    /// Recognize a line of `/proc/1/cgroup`, e.g. `0::/docker/<id>`.
+    fn from_cgroup(line: &str) -> Option<Self> {
+        // hierarchy-ID:controller-list:cgroup-path
+        let path = line.splitn(3, ':').nth(2)?;
+        let mut components = path.split('/').filter(|it| !it.is_empty()).rev();
+        let last = components.next()?;
+        let parent = components.next();
+        let first = path.split('/').find(|it| !it.is_empty())?;
+        if first == "lxc" || first.starts_with("lxc.payload.") {
+            Some(Container::Lxc)
+        } else if is_scope(last, "libpod-") {
+            // `machine.slice/libpod-<id>.scope` or `libpod_parent/libpod-<id>`
+            Some(Container::Podman)
+        } else if is_scope(last, "docker-") || (parent == Some("docker") && is_id(last)) {
+            // `system.slice/docker-<id>.scope` or `docker/<id>`
+            Some(Container::Docker)
+        } else if is_scope(last, "cri-containerd-") || is_scope(last, "nerdctl-") {
+            Some(Container::Containerd)
+        } else {
+            None
+        }
+    }
+
+    /// Recognize the line of `/proc/self/mountinfo` that mounts `/` from container storage.
+    ///
+    /// Only the root mount is looked at: a host that runs containers has their storage
+    /// mounted too.
+    fn from_mountinfo(line: &str) -> Option<Self> {
+        // mount-ID parent-ID major:minor root mount-point options... - fs-type source super-options
+        if line.split(' ').nth(4) != Some("/") {
+            return None;
+        }
+        if line.contains("/docker/overlay2/") {
+            Some(Container::Docker)
+        } else if line.contains("/containers/storage/")
+            || line
+                .split(&['/', ' ', ',', '='][..])
+                .any(|segment| segment.starts_with("libpod-"))
+        {
+            Some(Container::Podman)
+        } else if line.contains("/io.containerd.snapshotter.") {
+            Some(Container::Containerd)
+        } else if line.contains("/lxc/") || line.contains("/lxd/") {
+            Some(Container::Lxc)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `component` is `<prefix><id>` or `<prefix><id>.scope`.
+fn is_scope(component: &str, prefix: &str) -> bool {
+    component
+        .strip_prefix(prefix)
+        .map(|it| it.strip_suffix(".scope").unwrap_or(it))
+        .is_some_and(is_id)
+}
+
+/// Whether `s` is a full container id: 64 hex digits.
+fn is_id(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|it| it.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "3f4ea8a06a5ab8d57acae5d9bb6cac0c52d1e5e1ebd2a5cbd1e8b25af3e05ef1";
+
+    #[test]
+    fn cgroup() {
+        let cases = [
+            (format!("12:pids:/docker/{}", ID), Some(Container::Docker)),
+            (
+                format!("0::/system.slice/docker-{}.scope", ID),
+                Some(Container::Docker),
+            ),
+            (
+                format!("0::/machine.slice/libpod-{}.scope", ID),
+                Some(Container::Podman),
+            ),
+            (
+                format!("1:name=systemd:/libpod_parent/libpod-{}", ID),
+                Some(Container::Podman),
+            ),
+            (
+                format!(
+                    "0::/kubepods.slice/kubepods-besteffort.slice/\
+                     kubepods-besteffort-pod1234.slice/cri-containerd-{}.scope",
+                    ID
+                ),
+                Some(Container::Containerd),
+            ),
+            ("0::/lxc.payload.runner".to_owned(), Some(Container::Lxc)),
+            ("4:cpu:/lxc/runner".to_owned(), Some(Container::Lxc)),
+            // The host's own services and PID 1.
+            ("0::/init.scope".to_owned(), None),
+            ("0::/system.slice/containerd.service".to_owned(), None),
+            ("0::/system.slice/docker.service".to_owned(), None),
+            ("0::/".to_owned(), None),
+        ];
+        for &(ref line, expected) in &cases {
+            assert_eq!(Container::from_cgroup(line), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn mountinfo() {
+        let docker_root = "1234 1000 0:52 / / rw,relatime master:1 - overlay overlay \
+            rw,lowerdir=/var/lib/docker/overlay2/l/ABC,upperdir=/var/lib/docker/overlay2/abc/diff";
+        assert_eq!(
+            Container::from_mountinfo(docker_root),
+            Some(Container::Docker)
+        );
+
+        let containerd_root = "700 600 0:40 / / rw - overlay overlay rw,lowerdir=\
+            /var/lib/containerd/io.containerd.snapshotter.v1.overlayfs/snapshots/1/fs";
+        assert_eq!(
+            Container::from_mountinfo(containerd_root),
+            Some(Container::Containerd)
+        );
+
+        let podman_root = "800 700 0:41 / / rw - overlay overlay rw,lowerdir=\
+            /run/libpod-storage/overlay/l/ABC";
+        assert_eq!(
+            Container::from_mountinfo(podman_root),
+            Some(Container::Podman)
+        );
+        // Only a `libpod-` path segment counts, not the word anywhere on the line.
+        let libpod_word = "800 700 0:41 / / rw - ext4 /dev/mapper/nolibpod rw";
+        assert_eq!(Container::from_mountinfo(libpod_word), None);
+
+        // A host running containers has their storage mounted, but not at `/`.
+        let host_root = "29 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw";
+        let host_container = "900 29 0:52 / /var/lib/docker/overlay2/abc/merged rw - overlay \
+            overlay rw,lowerdir=/var/lib/docker/overlay2/l/ABC";
+        assert_eq!(Container::from_mountinfo(host_root), None);
+        assert_eq!(Container::from_mountinfo(host_container), None);
+    }
+}
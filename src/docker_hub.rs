@@ -0,0 +1,124 @@
+use build::Build;
+use commit::Commit;
+use provenance::{self, Provenance};
+use provider::Provider;
+use runner::{Hosting, Runner};
+use trigger::Trigger;
+use vars::{Detect, Missing, Vars};
+
+/// Docker Hub automated builds
+///
+/// # References
+///
+/// - <https://docs.docker.com/docker-hub/builds/advanced/>
+/// - <https://github.com/codecov/codecov-bash/blob/8b76995ad4a95a61cecd4b049a448a402d91d197/codecov#L490-L500>
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct DockerHub {
+    /// The name of the branch or the tag that is currently being tested.
+    pub source_branch: String,
+    /// The SHA1 hash of the commit being tested.
+    pub source_commit: String,
+    /// The message from the commit being tested and built.
+    pub commit_msg: String,
+    /// The name of the Docker repository being built.
+    pub repo: String,
+    /// The Docker repository tag being built.
+    pub cache_tag: String,
+    /// The name and tag of the Docker repository being built.
+    /// (This variable is a combination of `DOCKER_REPO`:`CACHE_TAG`.)
+    pub image_name: String,
+    /// The dockerfile currently being built.
+    /// (Only available to build hooks.)
+    pub dockerfile_path: Option<String>,
+    /// A unique code identifying the current build.
+    /// (Only available to build hooks.)
+    pub build_code: Option<String>,
+    /// The kind of source repository being built, e.g. `git`.
+    /// (Only available to build hooks.)
+    pub source_type: Option<String>,
+    /// The Docker repository tag being built.
+    /// (Only available to build hooks.)
+    pub docker_tag: Option<String>,
+    /// The required variables that were missing or invalid, in lenient detection.
+    missing: Missing,
+}
+
+impl_from_env!(DockerHub);
+
+impl Detect for DockerHub {
+    const NAME: &'static str = "Docker Hub";
+
+    fn read(vars: &mut Vars) -> Option<Self> {
+        if !vars.markers(&[("DOCKER_REPO", None), ("IMAGE_NAME", None)]) {
+            return None;
+        }
+
+        Some(DockerHub {
+            source_branch: vars.required("SOURCE_BRANCH"),
+            source_commit: vars.required("SOURCE_COMMIT"),
+            commit_msg: vars.required("COMMIT_MSG"),
+            repo: vars.required("DOCKER_REPO"),
+            cache_tag: vars.required("CACHE_TAG"),
+            image_name: vars.required("IMAGE_NAME"),
+            dockerfile_path: vars.optional("DOCKERFILE_PATH"),
+            build_code: vars.optional("BUILD_CODE"),
+            source_type: vars.optional("SOURCE_TYPE"),
+            docker_tag: vars.optional("DOCKER_TAG"),
+            missing: vars.missing(),
+        })
+    }
+}
+
+impl Provider for DockerHub {
+    fn name(&self) -> &str {
+        DockerHub::NAME
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.missing.given("SOURCE_COMMIT", &*self.source_commit)
+    }
+
+    fn commit(&self) -> Option<Commit> {
+        let mut commit = Commit::new(self.commit_sha()?);
+        commit.set_message(&self.commit_msg);
+        Some(commit)
+    }
+
+    fn branch(&self) -> Option<&str> {
+        self.missing.given("SOURCE_BRANCH", &*self.source_branch)
+    }
+
+    fn build(&self) -> Build {
+        Build {
+            id: self.build_code.clone(),
+            ..Build::default()
+        }
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        // Docker Hub only builds what is pushed to the source repository.
+        Trigger::of_push(&self.git_ref())
+    }
+
+    fn runner(&self) -> Runner {
+        let mut runner = Runner::new();
+        runner.os = "linux".to_owned();
+        runner.hosting = Some(Hosting::Hosted);
+        runner
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        if !provenance::has_value(self, field) {
+            return None;
+        }
+        match field {
+            "commit_sha" => provenance::var("SOURCE_COMMIT"),
+            "commit" => provenance::derived("SOURCE_COMMIT and COMMIT_MSG"),
+            "branch" => provenance::var("SOURCE_BRANCH"),
+            "build" if self.build_code.is_some() => provenance::var("BUILD_CODE"),
+            "runner" => provenance::derived("DOCKER_REPO and IMAGE_NAME"),
+            _ => provenance::default(self, field),
+        }
+    }
+}
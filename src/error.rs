@@ -0,0 +1,236 @@
+use overrides::Wanted;
+use std::error::Error;
+use std::fmt;
+
+/// Why detection failed: what was found for every provider that was a candidate.
+///
+/// A provider is a candidate if at least one of its marker variables was found.
+#[derive(Clone, Debug, Default)]
+pub struct DetectError {
+    candidates: Vec<Candidate>,
+    provider_override: Option<String>,
+    config: Option<String>,
+    overrides: Vec<Warning>,
+}
+
+impl DetectError {
+    /// The providers that were candidates, in detection order.
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    /// The value of `CI_DETECTIVE_PROVIDER`, if it limited detection.
+    pub fn provider_override(&self) -> Option<&str> {
+        self.provider_override.as_deref()
+    }
+
+    /// Whether a provider was identified by its markers, even though its other variables failed.
+    pub(crate) fn identified(&self) -> bool {
+        self.candidates.iter().any(|it| it.identified)
+    }
+
+    /// Why the config file could not be loaded, if it couldn't;
+    /// the built-in providers were still tried.
+    ///
+    /// Only a config loaded with the `config` feature can fail.
+    pub fn config_error(&self) -> Option<&str> {
+        self.config.as_deref()
+    }
+
+    /// The `CI_DETECTIVE_*` overrides that could not be used, if that is what failed detection.
+    pub fn invalid_overrides(&self) -> &[Warning] {
+        &self.overrides
+    }
+
+    pub(crate) fn overrides(warnings: Vec<Warning>) -> Self {
+        DetectError {
+            overrides: warnings,
+            ..DetectError::default()
+        }
+    }
+
+    pub(crate) fn config(mut self, message: Option<String>) -> Self {
+        self.config = message;
+        self
+    }
+
+    pub(crate) fn wanted(mut self, wanted: Wanted) -> Self {
+        self.provider_override = wanted.into_value();
+        self
+    }
+
+    pub(crate) fn push(&mut self, candidate: Candidate) {
+        if !candidate.markers_found.is_empty() {
+            self.candidates.push(candidate);
+        }
+    }
+}
+
+impl From<Candidate> for DetectError {
+    fn from(candidate: Candidate) -> Self {
+        DetectError {
+            candidates: vec![candidate],
+            provider_override: None,
+            config: None,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for DetectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.overrides.is_empty() {
+            f.write_str("invalid CI_DETECTIVE_* overrides")?;
+            for warning in &self.overrides {
+                write!(f, "\n  {}", warning)?;
+            }
+            return Ok(());
+        }
+        match self.provider_override.as_deref() {
+            Some("none") => {
+                return write!(f, "CI detection disabled by CI_DETECTIVE_PROVIDER=none")
+            }
+            Some(name) => write!(f, "CI_DETECTIVE_PROVIDER={} was not detected", name)?,
+            None => write!(f, "no CI provider detected")?,
+        }
+        if self.candidates.is_empty() && self.provider_override.is_none() {
+            write!(f, ": no marker variables found")?;
+        }
+        if let Some(ref config) = self.config {
+            write!(f, "\n  the config file could not be loaded: {}", config)?;
+        }
+        for candidate in &self.candidates {
+            write!(f, "\n  {}", candidate)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for DetectError {}
+
+/// What was found in the environment for one provider.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Candidate {
+    /// The name of the provider, as in [`Provider::name`](crate::Provider::name).
+    pub provider: &'static str,
+    /// The marker variables identifying the provider that were found.
+    pub markers_found: Vec<String>,
+    /// The marker and required variables that are not set.
+    pub missing: Vec<String>,
+    /// The marker and required variables that are set to something unexpected.
+    pub invalid: Vec<InvalidVar>,
+    /// The marker and required variables that are set to something that is not valid Unicode.
+    pub not_unicode: Vec<String>,
+    /// Whether all of the marker variables were found, so only required variables failed.
+    pub(crate) identified: bool,
+}
+
+impl Candidate {
+    pub(crate) fn new(provider: &'static str) -> Self {
+        Candidate {
+            provider,
+            markers_found: Vec::new(),
+            missing: Vec::new(),
+            invalid: Vec::new(),
+            not_unicode: Vec::new(),
+            identified: false,
+        }
+    }
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: found {}",
+            self.provider,
+            self.markers_found.join(", ")
+        )?;
+        if !self.missing.is_empty() {
+            write!(f, "; missing {}", self.missing.join(", "))?;
+        }
+        for invalid in &self.invalid {
+            write!(f, "; {}", invalid)?;
+        }
+        if !self.not_unicode.is_empty() {
+            write!(f, "; not valid Unicode: {}", self.not_unicode.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A variable whose value could not be used.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct InvalidVar {
+    /// The name of the variable.
+    pub name: String,
+    /// The value of the variable.
+    pub value: String,
+    /// What the value should have been: a type like `bool`, or an exact value like `` `true` ``.
+    pub expected: String,
+}
+
+impl fmt::Display for InvalidVar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={:?} is not {}", self.name, self.value, self.expected)
+    }
+}
+
+/// A provider detected in lenient mode, with everything that was wrong with its variables.
+///
+/// Required fields whose variables are missing or invalid hold a placeholder,
+/// like an empty string, `0` or `false`; each of them has a warning.
+/// The [`Provider`](crate::Provider) accessors leave such fields unset.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Partial<T> {
+    /// The detected provider.
+    pub provider: T,
+    /// The share of the provider's marker and required variables that are set and valid,
+    /// from `0.0` to `1.0`.
+    pub confidence: f64,
+    /// What was missing or invalid, in the order it was read.
+    pub warnings: Vec<Warning>,
+}
+
+impl<T> Partial<T> {
+    /// Whether nothing was missing or invalid, not even an optional variable.
+    pub fn is_complete(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    pub(crate) fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Partial<U> {
+        Partial {
+            provider: f(self.provider),
+            confidence: self.confidence,
+            warnings: self.warnings,
+        }
+    }
+}
+
+/// A problem lenient detection skipped over.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A required variable is not set.
+    Missing(String),
+    /// A variable is set to something that could not be used.
+    Invalid(InvalidVar),
+    /// A variable is set to something that is not valid Unicode.
+    NotUnicode(String),
+    /// The config file could not be loaded, so its provider was not detected.
+    Config(String),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::Missing(ref name) => write!(f, "{} is not set", name),
+            Warning::Invalid(ref invalid) => invalid.fmt(f),
+            Warning::NotUnicode(ref name) => write!(f, "{} is not valid Unicode", name),
+            Warning::Config(ref err) => write!(f, "the config file could not be loaded: {}", err),
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use build::{Build, Job};
+use git_ref;
+use parallelism::Parallelism;
+use provenance::{self, Provenance};
+use provider::Provider;
+use repository::Repository;
+use trigger::Trigger;
+use vars::{Detect, Vars};
+
+/// Any CI, recognized by the conventional variables most of them set.
+///
+/// This is the fallback when no specific provider is recognized. It uses the same
+/// heuristic as the `ci-info` and `is-ci` packages: unless `CI` is set to `false`,
+/// any of `BUILD_ID`, `BUILD_NUMBER`, `CI`, `CI_APP_ID`, `CI_BUILD_ID`, `CI_BUILD_NUMBER`,
+/// `CI_NAME`, `CONTINUOUS_INTEGRATION` or `RUN_ID` being set means this is a CI.
+///
+/// # References
+///
+/// - <https://github.com/watson/ci-info/blob/master/index.js>
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Generic {
+    /// The name of the CI, from `CI_NAME`.
+    pub name: Option<String>,
+    /// The commit being built, from `GIT_COMMIT`.
+    pub git_commit: Option<String>,
+    /// The branch being built, from `BRANCH_NAME` or `GIT_BRANCH`.
+    pub branch: Option<String>,
+    /// The tag being built, from `TAG_NAME`.
+    pub tag: Option<String>,
+    /// The URL of the repository, from `GIT_URL`.
+    pub git_url: Option<String>,
+    /// The number of the build, from `BUILD_NUMBER` or `CI_BUILD_NUMBER`.
+    pub build_number: Option<u64>,
+    /// The id of the build, from `BUILD_ID`, `CI_BUILD_ID` or `RUN_ID`.
+    pub build_id: Option<String>,
+    /// A link to the build, from `BUILD_URL`.
+    pub build_url: Option<String>,
+    /// The name of the job, from `JOB_NAME`.
+    pub job_name: Option<String>,
+    /// What started the build, from `CI_PIPELINE_SOURCE` (GitLab), `BUILDKITE_SOURCE`,
+    /// `BUILD_REASON` (Azure Pipelines) or `GITHUB_EVENT_NAME`.
+    pub event: Option<String>,
+    /// Which shard this is, from the shard variables of GitLab, Heroku CI, Buildkite,
+    /// Azure Pipelines, Semaphore or Bitbucket Pipelines.
+    pub parallelism: Option<Parallelism>,
+    /// The variables `parallelism` was read from.
+    shard_vars: Option<(&'static str, &'static str)>,
+    /// The variables `branch`, `build_number`, `build_id` and `event` were read from.
+    sources: [Option<&'static str>; 4],
+}
+
+impl_from_env!(Generic);
+
+/// The shard variables of other CIs: a variable only that CI sets,
+/// the variables with the shard index and the number of shards, and the first index.
+const SHARD_VARS: &[(&str, &str, &str, usize)] = &[
+    ("HEROKU_TEST_RUN_ID", "CI_NODE_INDEX", "CI_NODE_TOTAL", 0),
+    ("GITLAB_CI", "CI_NODE_INDEX", "CI_NODE_TOTAL", 1),
+    (
+        "BUILDKITE",
+        "BUILDKITE_PARALLEL_JOB",
+        "BUILDKITE_PARALLEL_JOB_COUNT",
+        0,
+    ),
+    (
+        "TF_BUILD",
+        "SYSTEM_JOBPOSITIONINPHASE",
+        "SYSTEM_TOTALJOBSINPHASE",
+        1,
+    ),
+    ("SEMAPHORE", "SEMAPHORE_JOB_INDEX", "SEMAPHORE_JOB_COUNT", 1),
+    (
+        "BITBUCKET_BUILD_NUMBER",
+        "BITBUCKET_PARALLEL_STEP",
+        "BITBUCKET_PARALLEL_STEP_COUNT",
+        0,
+    ),
+];
+
+/// Read the shard variables of the first CI in [`SHARD_VARS`] whose own variable is set.
+fn read_shard(vars: &mut Vars) -> Option<(Parallelism, (&'static str, &'static str))> {
+    let &(_, index_var, total_var, first) = SHARD_VARS
+        .iter()
+        .find(|&&(marker, ..)| vars.is_set(marker))?;
+    let index = vars.optional::<usize>(index_var)?.checked_sub(first)?;
+    let total = vars.optional::<usize>(total_var)?;
+    if index >= total {
+        return None;
+    }
+    Some((Parallelism::new(index, total), (index_var, total_var)))
+}
+
+impl Detect for Generic {
+    const NAME: &'static str = "Generic CI";
+
+    fn read(vars: &mut Vars) -> Option<Self> {
+        if vars.optional::<String>("CI").as_deref() == Some("false") {
+            return None;
+        }
+        if !vars.any_marker(&[
+            "BUILD_ID",
+            "BUILD_NUMBER",
+            "CI",
+            "CI_APP_ID",
+            "CI_BUILD_ID",
+            "CI_BUILD_NUMBER",
+            "CI_NAME",
+            "CONTINUOUS_INTEGRATION",
+            "RUN_ID",
+        ]) {
+            return None;
+        }
+
+        let branch = vars.first_of(&["BRANCH_NAME", "GIT_BRANCH"]);
+        let build_number = vars.first_of(&["BUILD_NUMBER", "CI_BUILD_NUMBER"]);
+        let build_id = vars.first_of(&["BUILD_ID", "CI_BUILD_ID", "RUN_ID"]);
+        let event = vars.first_of(&[
+            "CI_PIPELINE_SOURCE",
+            "BUILDKITE_SOURCE",
+            "BUILD_REASON",
+            "GITHUB_EVENT_NAME",
+        ]);
+        let shard = read_shard(vars);
+        Some(Generic {
+            name: vars.optional("CI_NAME"),
+            git_commit: vars.optional("GIT_COMMIT"),
+            sources: [
+                branch.as_ref().map(|it| it.1),
+                build_number.as_ref().map(|it| it.1),
+                build_id.as_ref().map(|it| it.1),
+                event.as_ref().map(|it| it.1),
+            ],
+            branch: branch.map(|it| it.0),
+            tag: vars.optional("TAG_NAME"),
+            git_url: vars.optional("GIT_URL"),
+            build_number: build_number.map(|it| it.0),
+            build_id: build_id.map(|it| it.0),
+            build_url: vars.optional("BUILD_URL"),
+            job_name: vars.optional("JOB_NAME"),
+            event: event.map(|it| it.0),
+            parallelism: shard.map(|it| it.0),
+            shard_vars: shard.map(|it| it.1),
+        })
+    }
+}
+
+impl Provider for Generic {
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(Generic::NAME)
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.git_commit.as_deref()
+    }
+
+    fn branch(&self) -> Option<&str> {
+        self.branch.as_deref().map(git_ref::short_name)
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.build_number
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.build_url.as_deref()
+    }
+
+    fn build(&self) -> Build {
+        Build {
+            id: self.build_id.clone(),
+            number: self.build_number,
+            url: self.build_url.clone(),
+            ..Build::default()
+        }
+    }
+
+    fn repository(&self) -> Option<Repository> {
+        Repository::parse(self.git_url.as_ref()?)
+    }
+
+    fn job(&self) -> Option<Job> {
+        Some(Job {
+            name: Some(self.job_name.clone()?),
+            ..Job::default()
+        })
+    }
+
+    fn parallelism(&self) -> Option<Parallelism> {
+        self.parallelism
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        match self.event.as_deref().and_then(event_trigger) {
+            Some(Trigger::Push) if self.tag.is_some() => Some(Trigger::Tag),
+            Some(trigger) => Some(trigger),
+            None => self.tag.as_ref().map(|_| Trigger::Tag),
+        }
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        let [branch, build_number, build_id, event] = self.sources;
+        match field {
+            "commit_sha" if self.git_commit.is_some() => provenance::var("GIT_COMMIT"),
+            "branch" => provenance::var(branch?),
+            "tag" if self.tag.is_some() => provenance::var("TAG_NAME"),
+            "build_number" => provenance::var(build_number?),
+            "build_url" if self.build_url.is_some() => provenance::var("BUILD_URL"),
+            "build" => {
+                let url = self.build_url.as_ref().map(|_| "BUILD_URL");
+                let vars: Vec<_> = [build_id, build_number, url]
+                    .iter()
+                    .flatten()
+                    .cloned()
+                    .collect();
+                match *vars {
+                    [] => None,
+                    [var] => provenance::var(var),
+                    [ref init @ .., last] => {
+                        provenance::derived(&format!("{} and {}", init.join(", "), last))
+                    }
+                }
+            }
+            "repository" if self.repository().is_some() => provenance::derived("GIT_URL"),
+            "job" if self.job_name.is_some() => provenance::var("JOB_NAME"),
+            "parallelism" => {
+                let (index, total) = self.shard_vars?;
+                provenance::derived(&format!("{} and {}", index, total))
+            }
+            "trigger" if self.event.as_deref().and_then(event_trigger).is_some() => {
+                provenance::var(event?)
+            }
+            _ => provenance::default(self, field),
+        }
+    }
+}
+
+/// The trigger of an event, as named by the CIs whose event variables are read.
+fn event_trigger(event: &str) -> Option<Trigger> {
+    Some(match event {
+        "push" | "IndividualCI" | "BatchedCI" => Trigger::Push,
+        "merge_request_event"
+        | "external_pull_request_event"
+        | "pull_request"
+        | "pull_request_target"
+        | "PullRequest" => Trigger::PullRequest,
+        "schedule" | "Schedule" => Trigger::Schedule,
+        "web" | "ui" | "Manual" | "workflow_dispatch" => Trigger::Manual,
+        "api" => Trigger::Api,
+        "trigger" | "pipeline" | "parent_pipeline" | "trigger_job" | "BuildCompletion"
+        | "ResourceTrigger" | "workflow_run" => Trigger::Upstream,
+        _ => return None,
+    })
+}
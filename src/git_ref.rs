@@ -0,0 +1,106 @@
+/// What is being built, normalized across providers.
+///
+/// Branch and tag names never carry a `refs/heads/`, `refs/tags/` or `origin/` prefix.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum GitRef {
+    /// A branch, e.g. `master`.
+    Branch(String),
+    /// A tag, e.g. `v1.0.0`.
+    Tag(String),
+    /// A pull request.
+    PullRequest {
+        /// The number of the pull request.
+        number: u64,
+        /// The branch the pull request comes from, if known.
+        source: Option<String>,
+        /// The branch the pull request wants to merge into, if known.
+        target: Option<String>,
+    },
+    /// A commit that is not on a branch, or the provider didn't say.
+    Detached,
+}
+
+impl GitRef {
+    /// A branch, with any ref prefix removed.
+    pub(crate) fn branch(name: &str) -> Self {
+        GitRef::Branch(short_name(name).to_owned())
+    }
+
+    /// A tag, with any ref prefix removed.
+    pub(crate) fn tag(name: &str) -> Self {
+        GitRef::Tag(short_name(name).to_owned())
+    }
+
+    /// A pull request, with any ref prefix removed from the branches.
+    pub(crate) fn pull_request(number: u64, source: Option<&str>, target: Option<&str>) -> Self {
+        GitRef::PullRequest {
+            number,
+            source: source.map(|it| short_name(it).to_owned()),
+            target: target.map(|it| short_name(it).to_owned()),
+        }
+    }
+
+    /// The branch name, if this is a branch.
+    pub fn as_branch(&self) -> Option<&str> {
+        match *self {
+            GitRef::Branch(ref name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The tag name, if this is a tag.
+    pub fn as_tag(&self) -> Option<&str> {
+        match *self {
+            GitRef::Tag(ref name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The pull request number, if this is a pull request.
+    pub fn pull_request_number(&self) -> Option<u64> {
+        match *self {
+            GitRef::PullRequest { number, .. } => Some(number),
+            _ => None,
+        }
+    }
+}
+
+/// Remove the `refs/heads/`, `refs/tags/` or `origin/` prefix from a ref name.
+pub(crate) fn short_name(name: &str) -> &str {
+    const PREFIXES: &[&str] = &[
+        "refs/heads/",
+        "refs/tags/",
+        "refs/remotes/origin/",
+        "remotes/origin/",
+        "origin/",
+    ];
+    PREFIXES
+        .iter()
+        .filter_map(|prefix| name.strip_prefix(prefix))
+        .next()
+        .unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_names() {
+        let cases = [
+            ("main", "main"),
+            ("refs/heads/main", "main"),
+            ("refs/tags/v1.0.0", "v1.0.0"),
+            ("origin/feature/x", "feature/x"),
+            ("remotes/origin/main", "main"),
+            ("refs/remotes/origin/main", "main"),
+            // Only one prefix is removed.
+            ("origin/origin/main", "origin/main"),
+            ("refs/pull/1/merge", "refs/pull/1/merge"),
+        ];
+        for &(name, short) in &cases {
+            assert_eq!(short_name(name), short, "{}", name);
+        }
+    }
+}
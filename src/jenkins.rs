@@ -1,5 +1,14 @@
-use env;
+use build::{Build, Job};
+use commit::Commit;
+use git_ref;
+use provenance::{self, Provenance};
+use provider::Provider;
+use pull_request::PullRequest;
+use repository::Repository;
+use runner::{Hosting, Runner};
 use std::path::PathBuf;
+use trigger::Trigger;
+use vars::{Detect, Empty, Missing, Vars};
 
 /// Jenkins CI
 ///
@@ -8,7 +17,7 @@ use std::path::PathBuf;
 /// - <https://wiki.jenkins.io/display/JENKINS/Building+a+software+project#Buildingasoftwareproject-belowJenkinsSetEnvironmentVariables>
 /// - <https://github.com/codecov/codecov-bash/blob/8b76995ad4a95a61cecd4b049a448a402d91d197/codecov#L430-L466>
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[non_exhaustive]
 pub struct Jenkins {
     /// The current build number, such as `153`
     pub build_number: usize,
@@ -52,32 +61,206 @@ pub struct Jenkins {
     /// For Git-based projects, this variable contains the Git branch
     /// that was checked out for the build (normally `origin/master`)
     pub git_branch: Option<String>,
+    /// Why the build was started, e.g. `MANUALTRIGGER` or `UPSTREAMTRIGGER`,
+    /// comma-separated if there are several causes.
+    /// (requires the EnvInject plugin)
+    pub build_cause: Option<String>,
+    /// Why the first build in a chain of upstream builds was started.
+    /// (requires the EnvInject plugin)
+    pub root_build_cause: Option<String>,
     /// Jenkins GitHub pull request builder plugin settings
     pub ghprb: Option<GHPRB>,
-    non_exhaustive: (),
+    /// The required variables that were missing or invalid, in lenient detection.
+    missing: Missing,
 }
 
-impl Jenkins {
-    /// Construct this provider's information from the environment.
-    pub fn from_env() -> Option<Self> {
+impl_from_env!(Jenkins);
+
+impl Detect for Jenkins {
+    const NAME: &'static str = "Jenkins";
+
+    fn read(vars: &mut Vars) -> Option<Self> {
+        if !vars.markers(&[("JENKINS_URL", None)]) {
+            return None;
+        }
+
         Some(Jenkins {
-            build_number: env("BUILD_NUMBER")?.parse().ok()?,
-            build_id: env("BUILD_ID")?,
-            build_url: env("BUILD_URL")?,
-            node_name: env("NODE_NAME")?,
-            job_name: env("JOB_NAME")?,
-            build_tag: env("BUILD_TAG")?,
-            jenkins_url: env("JENKINS_URL")?,
-            executor_number: env("EXECUTOR_NUMBER")?,
-            workspace: env("WORKSPACE")?.into(),
-            svn_revision: env("SVN_REVISION"),
-            cvs_branch: env("CVS_BRANCH"),
-            git_commit: env("GIT_COMMIT"),
-            git_url: env("GIT_URL"),
-            git_branch: env("GIT_BRANCH"),
-            ghprb: GHPRB::from_env(),
-            non_exhaustive: (),
+            build_number: vars.required("BUILD_NUMBER"),
+            build_id: vars.required("BUILD_ID"),
+            build_url: vars.required("BUILD_URL"),
+            node_name: vars.required("NODE_NAME"),
+            job_name: vars.required("JOB_NAME"),
+            build_tag: vars.required("BUILD_TAG"),
+            jenkins_url: vars.required("JENKINS_URL"),
+            executor_number: vars.required("EXECUTOR_NUMBER"),
+            workspace: vars.required_path("WORKSPACE"),
+            svn_revision: vars.optional("SVN_REVISION"),
+            cvs_branch: vars.optional("CVS_BRANCH"),
+            git_commit: vars.optional("GIT_COMMIT"),
+            git_url: vars.optional("GIT_URL"),
+            git_branch: vars.optional("GIT_BRANCH"),
+            build_cause: vars.optional("BUILD_CAUSE"),
+            root_build_cause: vars.optional("ROOT_BUILD_CAUSE"),
+            ghprb: vars.group(),
+            missing: vars.missing(),
+        })
+    }
+}
+
+impl Jenkins {
+    /// The trigger from the build's cause, and the variable it was read from.
+    fn cause(&self) -> Option<(Trigger, &'static str)> {
+        let direct = self.build_cause.as_deref().and_then(cause_trigger);
+        match direct {
+            Some(trigger) => Some((trigger, "BUILD_CAUSE")),
+            None => Some((
+                cause_trigger(self.root_build_cause.as_deref()?)?,
+                "ROOT_BUILD_CAUSE",
+            )),
+        }
+    }
+}
+
+/// The trigger of the first cause in `causes` that means one, like `TIMERTRIGGER`.
+fn cause_trigger(causes: &str) -> Option<Trigger> {
+    causes.split(',').find_map(|cause| {
+        Some(match cause.trim() {
+            "MANUALTRIGGER" | "USERIDCAUSE" => Trigger::Manual,
+            "TIMERTRIGGER" => Trigger::Schedule,
+            "SCMTRIGGER" | "GITHUBPUSHCAUSE" => Trigger::Push,
+            "UPSTREAMTRIGGER" => Trigger::Upstream,
+            "REMOTECAUSE" => Trigger::Api,
+            "GHPRBCAUSE" => Trigger::PullRequest,
+            "REBUILDCAUSE" | "REPLAYCAUSE" => Trigger::Rebuild,
+            _ => return None,
         })
+    })
+}
+
+impl Provider for Jenkins {
+    fn name(&self) -> &str {
+        Jenkins::NAME
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.git_commit
+            .as_deref()
+            .or_else(|| self.ghprb.as_ref()?.actual_commit())
+    }
+
+    fn commit(&self) -> Option<Commit> {
+        let mut commit = Commit::new(self.commit_sha()?);
+        if let Some(ref ghprb) = self.ghprb {
+            if ghprb.actual_commit() == Some(&*commit.sha) {
+                commit.author_name = ghprb.missing.given(
+                    "ghprbActualCommitAuthor",
+                    ghprb.actual_commit_author.clone(),
+                );
+                commit.author_email = ghprb.missing.given(
+                    "ghprbActualCommitAuthorEmail",
+                    ghprb.actual_commit_author_email.clone(),
+                );
+            }
+        }
+        Some(commit)
+    }
+
+    fn branch(&self) -> Option<&str> {
+        match self.ghprb {
+            Some(ref ghprb) => ghprb
+                .missing
+                .given("ghprbSourceBranch", &*ghprb.source_branch),
+            None => self.git_branch.as_deref().map(git_ref::short_name),
+        }
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        let ghprb = self.ghprb.as_ref()?;
+        let mut pr = PullRequest::new(ghprb.pull_id.parse().ok()?);
+        let missing = &ghprb.missing;
+        pr.url = missing.given("ghprbPullLink", ghprb.pull_link.clone());
+        pr.title = missing.given("ghprbPullTitle", ghprb.pull_title.clone());
+        pr.source_branch = missing.given("ghprbSourceBranch", ghprb.source_branch.clone());
+        pr.target_branch = missing.given("ghprbTargetBranch", ghprb.target_branch.clone());
+        pr.head_sha = ghprb.actual_commit().map(str::to_owned);
+        Some(pr)
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.missing.given("BUILD_NUMBER", self.build_number as u64)
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.missing.given("BUILD_URL", &*self.build_url)
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        self.repository().map(|repo| repo.slug())
+    }
+
+    fn repository(&self) -> Option<Repository> {
+        Repository::parse(self.git_url.as_ref()?)
+    }
+
+    fn build(&self) -> Build {
+        Build {
+            id: self.missing.given("BUILD_ID", self.build_id.clone()),
+            number: self.build_number(),
+            url: self.build_url().map(str::to_owned),
+            ..Build::default()
+        }
+    }
+
+    fn job(&self) -> Option<Job> {
+        let job = Job {
+            id: self.missing.given("BUILD_TAG", self.build_tag.clone()),
+            name: self.missing.given("JOB_NAME", self.job_name.clone()),
+            ..Job::default()
+        };
+        Some(job).filter(|job| *job != Job::default())
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        match self.cause() {
+            Some((trigger, _)) => Some(trigger),
+            None => self.pull_request().map(|_| Trigger::PullRequest),
+        }
+    }
+
+    fn runner(&self) -> Runner {
+        let mut runner = Runner::new();
+        runner.name = self.missing.given("NODE_NAME", self.node_name.clone());
+        runner.executor = self
+            .missing
+            .given("EXECUTOR_NUMBER", self.executor_number.clone());
+        runner.hosting = Some(Hosting::SelfHosted);
+        runner
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        if !provenance::has_value(self, field) {
+            return None;
+        }
+        match field {
+            "commit_sha" if self.git_commit.is_some() => provenance::var("GIT_COMMIT"),
+            "commit_sha" if self.ghprb.is_some() => provenance::var("ghprbActualCommit"),
+            "branch" if self.ghprb.is_some() => provenance::var("ghprbSourceBranch"),
+            "branch" if self.git_branch.is_some() => provenance::var("GIT_BRANCH"),
+            "pull_request" if self.pull_request().is_some() => provenance::var("ghprbPullId"),
+            "build_number" => provenance::var("BUILD_NUMBER"),
+            "build_url" => provenance::var("BUILD_URL"),
+            "repo_slug" | "repository" if self.repository().is_some() => {
+                provenance::derived("GIT_URL")
+            }
+            "build" => provenance::derived("BUILD_ID, BUILD_NUMBER and BUILD_URL"),
+            "job" => provenance::derived("BUILD_TAG and JOB_NAME"),
+            "trigger" if self.cause().is_some() => provenance::var(self.cause()?.1),
+            "trigger" => provenance::var("ghprbPullId"),
+            "runner" if self.runner().name.is_some() || self.runner().executor.is_some() => {
+                provenance::derived("NODE_NAME and EXECUTOR_NUMBER")
+            }
+            _ => provenance::default(self, field),
+        }
     }
 }
 
@@ -88,7 +271,7 @@ impl Jenkins {
 /// - <https://wiki.jenkins-ci.org/display/JENKINS/GitHub+pull+request+builder+plugin#GitHubpullrequestbuilderplugin-EnvironmentVariables>
 /// - <https://github.com/codecov/codecov-bash/blob/8b76995ad4a95a61cecd4b049a448a402d91d197/codecov#L430-L466>
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[non_exhaustive]
 #[allow(missing_docs)]
 pub struct GHPRB {
     pub actual_commit: String,
@@ -102,25 +285,41 @@ pub struct GHPRB {
     pub target_branch: String,
     pub comment_body: String,
     pub sha1: String,
-    non_exhaustive: (),
+    /// The required variables that were missing or invalid, in lenient detection.
+    missing: Missing,
 }
 
+impl_from_env!(GHPRB);
+
 impl GHPRB {
-    /// Construct this provider's information from the environment.
-    pub fn from_env() -> Option<Self> {
+    /// The commit being built, unless `ghprbActualCommit` was missing or invalid.
+    fn actual_commit(&self) -> Option<&str> {
+        self.missing
+            .given("ghprbActualCommit", &*self.actual_commit)
+    }
+}
+
+impl Detect for GHPRB {
+    const NAME: &'static str = "GitHub pull request builder";
+
+    fn read(vars: &mut Vars) -> Option<Self> {
+        if !vars.markers(&[("ghprbPullId", None)]) {
+            return None;
+        }
+
         Some(GHPRB {
-            actual_commit: env("ghprbActualCommit")?,
-            actual_commit_author: env("ghprbActualCommitAuthor")?,
-            actual_commit_author_email: env("ghprbActualCommitAuthorEmail")?,
-            pull_description: env("ghprbPullDescription")?,
-            pull_id: env("ghprbPullId")?,
-            pull_link: env("ghprbPullLink")?,
-            pull_title: env("ghprbPullTitle")?,
-            source_branch: env("ghprbSourceBranch")?,
-            target_branch: env("ghprbTargetBranch")?,
-            comment_body: env("ghprbCommentBody")?,
-            sha1: env("sha1")?,
-            non_exhaustive: (),
+            actual_commit: vars.required("ghprbActualCommit"),
+            actual_commit_author: vars.required("ghprbActualCommitAuthor"),
+            actual_commit_author_email: vars.required("ghprbActualCommitAuthorEmail"),
+            pull_description: vars.required("ghprbPullDescription"),
+            pull_id: vars.required("ghprbPullId"),
+            pull_link: vars.required("ghprbPullLink"),
+            pull_title: vars.required("ghprbPullTitle"),
+            source_branch: vars.required("ghprbSourceBranch"),
+            target_branch: vars.required("ghprbTargetBranch"),
+            comment_body: vars.required_with(Empty::Present, "ghprbCommentBody"),
+            sha1: vars.required("sha1"),
+            missing: vars.missing(),
         })
     }
 }
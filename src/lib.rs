@@ -11,54 +11,403 @@
 #![warn(unsafe_code)]
 #![warn(unused)]
 #![warn(unused_extern_crates)]
-#![cfg_attr(feature = "nightly", feature(non_exhaustive))]
+
+#[cfg(feature = "config")]
+extern crate regex;
+#[cfg(feature = "config")]
+extern crate toml;
 
 /// Grab the configuration from whatever CI you're on.
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[non_exhaustive]
+#[allow(clippy::large_enum_variant)] // there is only ever one CI
 pub enum CI {
     /// Jenkins CI
-    Jenkins(Jenkins),
+    Jenkins(Detected<Jenkins>),
     /// Travis CI
-    Travis(Travis),
-    /// Docker
-    Docker(Docker),
+    Travis(Detected<Travis>),
+    /// Docker Hub automated build
+    DockerHub(Detected<DockerHub>),
     /// Codeship CI
-    Codeship(Codeship),
+    Codeship(Detected<Codeship>),
     /// Codefresh CI
-    Codefresh(Codefresh),
+    Codefresh(Detected<Codefresh>),
     /// Circle CI
-    Circle(Circle),
+    Circle(Detected<Circle>),
     /// Appveyor CI
-    Appveyor(Appveyor),
-    #[doc(hidden)]
-    __NonExhaustive,
+    Appveyor(Detected<Appveyor>),
+    /// Any other CI, recognized by conventional variables like `CI` and `BUILD_NUMBER`.
+    ///
+    /// This is only detected if no specific provider is identified by its marker variables.
+    Generic(Detected<Generic>),
+    /// A provider registered with [`CI::register`].
+    Custom(Detected<Box<dyn Provider>>),
 }
 
 impl CI {
     /// Grab the CI environment information
-    #[cfg_attr(rustfmt, rustfmt_skip)]
     pub fn from_env() -> Option<Self> {
-        None
-            .or_else(|| Jenkins  ::from_env().map(CI::Jenkins  ))
-            .or_else(|| Travis   ::from_env().map(CI::Travis   ))
-            .or_else(|| Docker   ::from_env().map(CI::Docker   ))
-            .or_else(|| Codeship ::from_env().map(CI::Codeship ))
-            .or_else(|| Codefresh::from_env().map(CI::Codefresh))
-            .or_else(|| Circle   ::from_env().map(CI::Circle   ))
-            .or_else(|| Appveyor ::from_env().map(CI::Appveyor ))
+        CI::try_from_env().ok()
+    }
+
+    /// Grab the CI environment information,
+    /// explaining why no provider matched if none does.
+    ///
+    /// The environment is read once, into a [`Snapshot`], and all providers are detected from that.
+    ///
+    /// Detection can be overridden through the environment:
+    ///
+    /// - `CI_DETECTIVE_PROVIDER=none` pretends not to be on a CI,
+    /// - `CI_DETECTIVE_PROVIDER=<name>` only detects the provider with this name, like `travis`,
+    /// - `CI_DETECTIVE_COMMIT`, `CI_DETECTIVE_BRANCH`, `CI_DETECTIVE_TAG` and `CI_DETECTIVE_PR`
+    ///   replace what the detected provider reports, see [`Overrides`].
+    ///
+    /// With the `config` feature, a config file that can't be loaded is skipped and the
+    /// built-in providers are still detected. If none is, the error says why,
+    /// see [`DetectError::config_error`]; lenient detection reports it as a warning.
+    pub fn try_from_env() -> Result<Self, DetectError> {
+        CI::try_from_source(&Snapshot::capture())
+    }
+
+    /// Add a provider to detection.
+    ///
+    /// `detect` is tried in order of `priority`, highest first, with providers of the same
+    /// priority in the order they were registered. The built-in providers have priority `0`:
+    /// providers with a higher priority are tried before them, the others after them,
+    /// but still before the [`Generic`] fallback.
+    /// With the `config` feature, a provider declared in a `ci-detective.toml` file
+    /// is tried before all of them.
+    ///
+    /// Registration is process-wide, and doesn't invalidate [`current`](CI::current).
+    /// A custom detector may itself detect the CI, e.g. with [`is_ci`]; that skips the
+    /// custom providers, including the one calling it.
+    pub fn register(priority: i32, detect: CustomDetector) {
+        chain::register(priority, detect)
+    }
+
+    /// The CI environment information of this process, detected once and then cached.
+    ///
+    /// Changes to the environment after the first call are not seen;
+    /// use [`invalidate_current`](CI::invalidate_current) to detect again.
+    ///
+    /// Detection runs without holding the cache's lock, so a custom detector may call this.
+    /// It then detects again without caching, and without the custom providers.
+    pub fn current() -> Option<Self> {
+        let generation = {
+            let current = lock_current();
+            if let Some(ref ci) = current.ci {
+                return ci.clone();
+            }
+            current.generation
+        };
+        let ci = match chain::Reentry::enter(&DETECTING) {
+            Some(_detecting) => CI::from_env(),
+            // Called by a custom detector, which doesn't see itself or the other custom ones.
+            None => return CI::from_env(),
+        };
+
+        let mut current = lock_current();
+        // Another thread may have detected first, or the cache may have been invalidated since.
+        if current.generation != generation {
+            return ci;
+        }
+        current.ci.get_or_insert(ci).clone()
+    }
+
+    /// Forget the cached result of [`current`](CI::current), so the next call detects again.
+    ///
+    /// This is meant for tests that change the environment.
+    pub fn invalidate_current() {
+        let mut current = lock_current();
+        current.ci = None;
+        current.generation += 1;
+    }
+
+    /// Grab the CI environment information from the first provider whose marker
+    /// variables are found, even if some of its other variables are missing or invalid.
+    ///
+    /// What could not be read is listed in the warnings; see [`Partial`].
+    pub fn from_env_lenient() -> Option<Partial<Self>> {
+        CI::from_source_lenient(&Snapshot::capture())
+    }
+
+    /// Grab the CI information from the given variables instead of the process environment.
+    pub fn from_source<S: EnvSource + ?Sized>(source: &S) -> Option<Self> {
+        CI::try_from_source(source).ok()
+    }
+
+    /// Like [`try_from_env`](CI::try_from_env), but reading the given variables.
+    pub fn try_from_source<S: EnvSource + ?Sized>(source: &S) -> Result<Self, DetectError> {
+        let source = &ByRef(source);
+        let (overrides, warnings) = Overrides::from_source(source);
+        if !warnings.is_empty() {
+            return Err(DetectError::overrides(warnings));
+        }
+        chain::strict(source).map(|ci| ci.with_overrides(&overrides))
+    }
+
+    /// Like [`from_env_lenient`](CI::from_env_lenient), but reading the given variables.
+    pub fn from_source_lenient<S: EnvSource + ?Sized>(source: &S) -> Option<Partial<Self>> {
+        let source = &ByRef(source);
+        let (overrides, warnings) = Overrides::from_source(source);
+        chain::lenient(source).map(|mut partial| {
+            partial.warnings.extend(warnings);
+            partial.map(|ci| ci.with_overrides(&overrides))
+        })
+    }
+
+    /// Every provider whose marker variables are found, best match first.
+    ///
+    /// This helps with layered setups, like a Docker Hub build inside another CI.
+    /// Providers are ranked by [`confidence`](Partial::confidence): the share of their
+    /// marker and required variables that are set and valid. Providers with the same
+    /// confidence keep the order [`from_env`](CI::from_env) tries them in.
+    /// The [`Generic`] fallback has no required variables, so its confidence is `0.0`
+    /// and it always comes last. Registered providers don't report what they matched,
+    /// so their confidence is `1.0`.
+    pub fn detect_all() -> Vec<Partial<Self>> {
+        CI::detect_all_from_source(&Snapshot::capture())
+    }
+
+    /// Like [`detect_all`](CI::detect_all), but reading the given variables.
+    pub fn detect_all_from_source<S: EnvSource + ?Sized>(source: &S) -> Vec<Partial<Self>> {
+        let source = &ByRef(source);
+        let (overrides, warnings) = Overrides::from_source(source);
+        let mut all: Vec<_> = chain::all(source)
+            .into_iter()
+            .map(|mut partial| {
+                partial.warnings.extend(warnings.iter().cloned());
+                partial.map(|ci| ci.with_overrides(&overrides))
+            })
+            .collect();
+        // `sort_by` is stable, so ties stay in detection order.
+        all.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        all
+    }
+
+    /// Whether values were replaced through `CI_DETECTIVE_*` variables.
+    pub fn is_overridden(&self) -> bool {
+        !self.overrides().is_empty()
+    }
+
+    /// The values replaced through `CI_DETECTIVE_*` variables.
+    pub fn overrides(&self) -> &Overrides {
+        match *self {
+            CI::Jenkins(ref ci) => ci.overrides(),
+            CI::Travis(ref ci) => ci.overrides(),
+            CI::DockerHub(ref ci) => ci.overrides(),
+            CI::Codeship(ref ci) => ci.overrides(),
+            CI::Codefresh(ref ci) => ci.overrides(),
+            CI::Circle(ref ci) => ci.overrides(),
+            CI::Appveyor(ref ci) => ci.overrides(),
+            CI::Generic(ref ci) => ci.overrides(),
+            CI::Custom(ref ci) => ci.overrides(),
+        }
+    }
+
+    fn overrides_mut(&mut self) -> &mut Overrides {
+        match *self {
+            CI::Jenkins(ref mut ci) => ci.overrides_mut(),
+            CI::Travis(ref mut ci) => ci.overrides_mut(),
+            CI::DockerHub(ref mut ci) => ci.overrides_mut(),
+            CI::Codeship(ref mut ci) => ci.overrides_mut(),
+            CI::Codefresh(ref mut ci) => ci.overrides_mut(),
+            CI::Circle(ref mut ci) => ci.overrides_mut(),
+            CI::Appveyor(ref mut ci) => ci.overrides_mut(),
+            CI::Generic(ref mut ci) => ci.overrides_mut(),
+            CI::Custom(ref mut ci) => ci.overrides_mut(),
+        }
+    }
+
+    /// Replace values with the `CI_DETECTIVE_*` overrides.
+    fn with_overrides(mut self, overrides: &Overrides) -> Self {
+        *self.overrides_mut() = overrides.clone();
+        self
+    }
+
+    /// The detected provider, as a vendor-neutral [`Provider`](trait.Provider.html).
+    pub fn provider(&self) -> &dyn Provider {
+        match *self {
+            CI::Jenkins(ref ci) => ci,
+            CI::Travis(ref ci) => ci,
+            CI::DockerHub(ref ci) => ci,
+            CI::Codeship(ref ci) => ci,
+            CI::Codefresh(ref ci) => ci,
+            CI::Circle(ref ci) => ci,
+            CI::Appveyor(ref ci) => ci,
+            CI::Generic(ref ci) => ci,
+            CI::Custom(ref ci) => ci,
+        }
     }
 }
 
-fn env(var: &str) -> Option<String> {
-    let env_var = std::env::var(var).unwrap_or_default();
-    if !env_var.is_empty() {
-        Some(env_var)
-    } else {
-        None
+impl Provider for CI {
+    fn name(&self) -> &str {
+        self.provider().name()
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.provider().commit_sha()
+    }
+
+    fn commit(&self) -> Option<Commit> {
+        self.provider().commit()
+    }
+
+    fn branch(&self) -> Option<&str> {
+        self.provider().branch()
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.provider().tag()
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        self.provider().pull_request()
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.provider().build_number()
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.provider().build_url()
+    }
+
+    fn build(&self) -> Build {
+        self.provider().build()
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        self.provider().repo_slug()
+    }
+
+    fn repository(&self) -> Option<Repository> {
+        self.provider().repository()
+    }
+
+    fn job_id(&self) -> Option<&str> {
+        self.provider().job_id()
+    }
+
+    fn job(&self) -> Option<Job> {
+        self.provider().job()
+    }
+
+    fn git_ref(&self) -> GitRef {
+        self.provider().git_ref()
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        self.provider().trigger()
+    }
+
+    fn runner(&self) -> Runner {
+        self.provider().runner()
     }
+
+    fn parallelism(&self) -> Option<Parallelism> {
+        self.provider().parallelism()
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        self.provider().provenance(field)
+    }
+}
+
+/// Whether this process is running on a CI.
+///
+/// This uses the same heuristic as the `ci-info` and `is-ci` packages:
+/// it is a CI if `CI` is not set to `false`, and either a provider is detected
+/// or one of the conventional variables listed on [`Generic`] is set.
+pub fn is_ci() -> bool {
+    is_ci_source(&Snapshot::capture())
 }
 
+/// Like [`is_ci`], but reading the given variables.
+pub fn is_ci_source<S: EnvSource + ?Sized>(source: &S) -> bool {
+    let ci = source.var_os("CI");
+    ci.as_ref().and_then(|it| it.to_str()) != Some("false")
+        && CI::from_source_lenient(source).is_some()
+}
+
+/// The cache of `CI::current`.
+struct Current {
+    /// The result, once it has been computed.
+    ci: Option<Option<CI>>,
+    /// How often the cache was invalidated, so a detection that started before is not cached.
+    generation: u64,
+}
+
+static CURRENT: Mutex<Current> = Mutex::new(Current {
+    ci: None,
+    generation: 0,
+});
+
+thread_local! {
+    /// Whether this thread is detecting for `CI::current`.
+    static DETECTING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn lock_current() -> MutexGuard<'static, Current> {
+    CURRENT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+mod chain;
+#[macro_use]
+mod vars;
+use source::ByRef;
+use std::cell::Cell;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Vendor-neutral access to CI information
+pub mod provider;
+pub use provider::{CloneProvider, CustomDetector, Provider};
+
+/// Where environment variables are read from
+pub mod source;
+pub use source::{EnvSource, ProcessEnv, Snapshot};
+
+/// Diagnostics for failed detection
+pub mod error;
+pub use error::{Candidate, DetectError, InvalidVar, Partial, Warning};
+
+/// Normalized builds and jobs
+pub mod build;
+pub use build::{Build, Job};
+
+/// Normalized commits
+pub mod commit;
+pub use commit::Commit;
+
+/// Normalized git refs
+pub mod git_ref;
+pub use git_ref::GitRef;
+
+/// Normalized parallelism
+pub mod parallelism;
+pub use parallelism::Parallelism;
+
+/// Where normalized values come from
+pub mod provenance;
+pub use provenance::Provenance;
+
+/// Normalized pull requests
+pub mod pull_request;
+pub use pull_request::PullRequest;
+
+/// Normalized repositories
+pub mod repository;
+pub use repository::{Forge, Repository};
+
+/// Normalized runner information
+pub mod runner;
+pub use runner::{Hosting, Runner};
+
+/// Normalized build triggers
+pub mod trigger;
+pub use trigger::Trigger;
+
 /// Jenkins CI
 pub mod jenkins;
 pub use jenkins::Jenkins;
@@ -67,9 +416,23 @@ pub use jenkins::Jenkins;
 pub mod travis;
 pub use travis::Travis;
 
-/// Docker
-pub mod docker;
-pub use docker::Docker;
+/// Docker Hub automated builds
+pub mod docker_hub;
+pub use docker_hub::DockerHub;
+
+/// Docker Hub automated builds, under their old name
+#[deprecated(note = "renamed to `docker_hub`")]
+pub mod docker {
+    pub use docker_hub::*;
+
+    /// Docker Hub automated build, under its old name
+    #[deprecated(note = "renamed to `DockerHub`")]
+    pub type Docker = ::DockerHub;
+}
+
+/// Docker Hub automated build, under its old name
+#[deprecated(note = "renamed to `DockerHub`")]
+pub type Docker = DockerHub;
 
 /// Codeship CI
 pub mod codeship;
@@ -89,3 +452,21 @@ pub use circle::Circle;
 /// Appveyor CI
 pub mod appveyor;
 pub use appveyor::Appveyor;
+
+/// Declarative providers from a config file
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "config")]
+pub use config::{Config, ConfigError, Declared};
+
+/// Environment overrides
+pub mod overrides;
+pub use overrides::{Detected, Overrides};
+
+/// Generic CI fallback
+pub mod generic;
+pub use generic::Generic;
+
+/// Container runtime detection
+pub mod container;
+pub use container::Container;
@@ -1,7 +1,110 @@
 extern crate ci_detective;
 
+use ci_detective::provenance::FIELDS;
+use ci_detective::{Provider, Snapshot, CI};
+
 fn main() {
-    if let Some(ci) = ci_detective::CI::from_env() {
-        println!("{:?}", ci);
+    let mut args = std::env::args().skip(1);
+    let arg = args.next();
+    #[cfg(feature = "config")]
+    {
+        if arg.as_deref() == Some("--check-config") {
+            check_config(args.next());
+            return;
+        }
+    }
+    let explain = arg.as_deref() == Some("--explain");
+
+    // One snapshot for both attempts, so the config file is only loaded once.
+    let env = Snapshot::capture();
+    let ci = match CI::try_from_source(&env) {
+        Ok(ci) => Some(ci),
+        Err(err) => match CI::from_source_lenient(&env) {
+            Some(partial) => {
+                for warning in &partial.warnings {
+                    eprintln!("warning: {}", warning);
+                }
+                Some(partial.provider)
+            }
+            None => {
+                eprintln!("{}", err);
+                None
+            }
+        },
+    };
+    match ci {
+        Some(ref ci) if explain => explain_fields(ci),
+        Some(ref ci) => println!("{:?}", ci),
+        None => {}
+    }
+    if let Some(container) = ci_detective::Container::detect() {
+        println!("{:?}", container);
+    }
+}
+
+/// Show every normalized field, and where its value came from.
+fn explain_fields(ci: &CI) {
+    println!("{}", ci.name());
+    for &field in FIELDS {
+        let value = match field {
+            "commit_sha" => ci.commit_sha().map(|it| format!("{:?}", it)),
+            "commit" => ci.commit().map(|it| format!("{:?}", it)),
+            "branch" => ci.branch().map(|it| format!("{:?}", it)),
+            "tag" => ci.tag().map(|it| format!("{:?}", it)),
+            "pull_request" => ci.pull_request().map(|it| format!("{:?}", it)),
+            "git_ref" => Some(format!("{:?}", ci.git_ref())),
+            "trigger" => ci.trigger().map(|it| format!("{:?}", it)),
+            "build_number" => ci.build_number().map(|it| format!("{:?}", it)),
+            "build_url" => ci.build_url().map(|it| format!("{:?}", it)),
+            "build" => Some(format!("{:?}", ci.build())),
+            "repo_slug" => ci.repo_slug().map(|it| format!("{:?}", it)),
+            "repository" => ci.repository().map(|it| format!("{:?}", it)),
+            "job_id" => ci.job_id().map(|it| format!("{:?}", it)),
+            "job" => ci.job().map(|it| format!("{:?}", it)),
+            "runner" => Some(format!("{:?}", ci.runner())),
+            "parallelism" => ci.parallelism().map(|it| format!("{:?}", it)),
+            _ => None,
+        };
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        match ci.provenance(field) {
+            Some(provenance) => println!("  {}: {} ({})", field, value, provenance),
+            None => println!("  {}: {}", field, value),
+        }
+    }
+}
+
+/// Validate a config file, and show what it declares in the current environment.
+#[cfg(feature = "config")]
+fn check_config(path: Option<String>) {
+    use ci_detective::Config;
+    use std::path::Path;
+    use std::process;
+
+    let config = match path {
+        Some(path) => Config::read(Path::new(&path)),
+        None => match Config::load() {
+            Ok(Some(config)) => Ok(config),
+            Ok(None) => {
+                eprintln!("no {} found", Config::FILE_NAME);
+                process::exit(1);
+            }
+            Err(err) => Err(err),
+        },
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("invalid config: {}", err);
+            process::exit(1);
+        }
+    };
+
+    println!("{}: valid", config.name());
+    match config.detect(&ci_detective::ProcessEnv) {
+        Some(declared) => println!("{:?}", declared),
+        None => println!("markers not found in this environment"),
     }
 }
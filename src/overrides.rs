@@ -0,0 +1,330 @@
+use build::{Build, Job};
+use commit::Commit;
+use error::{InvalidVar, Warning};
+use git_ref::GitRef;
+use parallelism::Parallelism;
+use provenance::{self, Provenance};
+use provider::Provider;
+use pull_request::PullRequest;
+use repository::Repository;
+use runner::Runner;
+use source::{self, EnvSource};
+use std::ops::Deref;
+use trigger::Trigger;
+
+/// A detected provider, with the values replaced through `CI_DETECTIVE_*` variables.
+///
+/// This dereferences to the provider, so its fields can be read directly;
+/// its [`Provider`] implementation reports the overridden values instead of the provider's.
+#[derive(Clone, Debug)]
+pub struct Detected<T> {
+    provider: T,
+    overrides: Overrides,
+}
+
+impl<T> Detected<T> {
+    pub(crate) fn new(provider: T) -> Self {
+        Detected {
+            provider,
+            overrides: Overrides::default(),
+        }
+    }
+
+    /// The values replaced through `CI_DETECTIVE_*` variables.
+    pub fn overrides(&self) -> &Overrides {
+        &self.overrides
+    }
+
+    pub(crate) fn overrides_mut(&mut self) -> &mut Overrides {
+        &mut self.overrides
+    }
+
+    /// Whether any value was replaced through `CI_DETECTIVE_*` variables.
+    pub fn is_overridden(&self) -> bool {
+        !self.overrides.is_empty()
+    }
+
+    /// The provider, without the overrides.
+    pub fn into_inner(self) -> T {
+        self.provider
+    }
+}
+
+impl<T> Deref for Detected<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.provider
+    }
+}
+
+/// The values `CI_DETECTIVE_COMMIT`, `CI_DETECTIVE_BRANCH`, `CI_DETECTIVE_TAG` and
+/// `CI_DETECTIVE_PR` (a pull request number) replace.
+///
+/// Overriding the branch, tag or pull request replaces what is being built as a whole:
+/// the provider's tag and pull request are not reported next to an overridden branch.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Overrides {
+    /// The commit SHA from `CI_DETECTIVE_COMMIT`.
+    pub commit: Option<String>,
+    /// The branch from `CI_DETECTIVE_BRANCH`.
+    pub branch: Option<String>,
+    /// The tag from `CI_DETECTIVE_TAG`.
+    pub tag: Option<String>,
+    /// The pull request number from `CI_DETECTIVE_PR`.
+    pub pull_request: Option<u64>,
+}
+
+impl Overrides {
+    /// Read the overrides from `source`, with a warning for every value that can't be used.
+    pub(crate) fn from_source(source: &dyn EnvSource) -> (Self, Vec<Warning>) {
+        let mut warnings = Vec::new();
+        let mut var = |name: &str| source::var(source, name, &mut warnings);
+        let commit = var("CI_DETECTIVE_COMMIT");
+        let branch = var("CI_DETECTIVE_BRANCH");
+        let tag = var("CI_DETECTIVE_TAG");
+        let pull_request = var("CI_DETECTIVE_PR");
+        // `CI_DETECTIVE_PROVIDER` is read by `Wanted`, and only checked here.
+        var("CI_DETECTIVE_PROVIDER");
+        let pull_request = pull_request.and_then(|value| match value.parse() {
+            Ok(number) => Some(number),
+            Err(_) => {
+                warnings.push(Warning::Invalid(InvalidVar {
+                    name: "CI_DETECTIVE_PR".to_owned(),
+                    value,
+                    expected: "a pull request number".to_owned(),
+                }));
+                None
+            }
+        });
+        let overrides = Overrides {
+            commit,
+            branch,
+            tag,
+            pull_request,
+        };
+        (overrides, warnings)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Overrides::default()
+    }
+
+    /// Whether what is being built is overridden, so it can't be taken from the provider.
+    fn overrides_ref(&self) -> bool {
+        self.branch.is_some() || self.tag.is_some() || self.pull_request.is_some()
+    }
+}
+
+/// Which provider `CI_DETECTIVE_PROVIDER` asks for.
+#[derive(Clone, Debug)]
+pub(crate) enum Wanted {
+    /// Whichever is detected; the variable is not set.
+    Any,
+    /// None at all: `none`.
+    Nothing,
+    /// The one with this name.
+    Named(String),
+}
+
+impl Wanted {
+    /// Read `CI_DETECTIVE_PROVIDER`.
+    ///
+    /// A value that is not valid Unicode matches no provider; it is reported by
+    /// [`Overrides::from_source`].
+    pub(crate) fn from_source(source: &dyn EnvSource) -> Self {
+        let name = match source.var_os("CI_DETECTIVE_PROVIDER") {
+            Some(ref name) if name.is_empty() => return Wanted::Any,
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => return Wanted::Any,
+        };
+        if name.eq_ignore_ascii_case("none") {
+            Wanted::Nothing
+        } else {
+            Wanted::Named(name)
+        }
+    }
+
+    /// Whether the provider with this name is allowed.
+    ///
+    /// Names are compared ignoring case, punctuation and a trailing `CI`,
+    /// so `travis`, `travis-ci` and `Travis CI` are all the same.
+    pub(crate) fn accepts(&self, name: &str) -> bool {
+        match *self {
+            Wanted::Any => true,
+            Wanted::Nothing => false,
+            Wanted::Named(ref wanted) => normalize(wanted) == normalize(name),
+        }
+    }
+
+    /// The value of `CI_DETECTIVE_PROVIDER`, if it limits detection.
+    pub(crate) fn into_value(self) -> Option<String> {
+        match self {
+            Wanted::Any => None,
+            Wanted::Nothing => Some("none".to_owned()),
+            Wanted::Named(name) => Some(name),
+        }
+    }
+}
+
+fn normalize(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|it| it.to_ascii_lowercase())
+        .collect();
+    match name.strip_suffix("ci") {
+        Some(stripped) if !stripped.is_empty() => stripped.to_owned(),
+        _ => name,
+    }
+}
+
+impl<T: Provider + Clone + 'static> Provider for Detected<T> {
+    fn name(&self) -> &str {
+        self.provider.name()
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.overrides
+            .commit
+            .as_deref()
+            .or_else(|| self.provider.commit_sha())
+    }
+
+    fn commit(&self) -> Option<Commit> {
+        match self.overrides.commit {
+            Some(ref sha) => Some(Commit::new(sha)),
+            None => self.provider.commit(),
+        }
+    }
+
+    fn branch(&self) -> Option<&str> {
+        self.overrides
+            .branch
+            .as_deref()
+            .or_else(|| self.provider.branch())
+    }
+
+    fn tag(&self) -> Option<&str> {
+        if self.overrides.overrides_ref() {
+            self.overrides.tag.as_deref()
+        } else {
+            self.provider.tag()
+        }
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        match self.overrides.pull_request {
+            Some(number) => {
+                let mut pr = PullRequest::new(number);
+                pr.source_branch = self.branch().map(String::from);
+                Some(pr)
+            }
+            None if self.overrides.overrides_ref() => None,
+            None => self.provider.pull_request(),
+        }
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.provider.build_number()
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.provider.build_url()
+    }
+
+    fn build(&self) -> Build {
+        self.provider.build()
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        self.provider.repo_slug()
+    }
+
+    fn repository(&self) -> Option<Repository> {
+        self.provider.repository()
+    }
+
+    fn job_id(&self) -> Option<&str> {
+        self.provider.job_id()
+    }
+
+    fn job(&self) -> Option<Job> {
+        self.provider.job()
+    }
+
+    fn git_ref(&self) -> GitRef {
+        if !self.overrides.overrides_ref() {
+            return self.provider.git_ref();
+        }
+        if let Some(pr) = self.pull_request() {
+            GitRef::pull_request(
+                pr.number,
+                pr.source_branch.as_deref(),
+                pr.target_branch.as_deref(),
+            )
+        } else if let Some(tag) = self.tag() {
+            GitRef::tag(tag)
+        } else {
+            GitRef::branch(self.branch().unwrap_or_default())
+        }
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        if self.overrides.pull_request.is_some() {
+            Some(Trigger::PullRequest)
+        } else if self.overrides.tag.is_some() {
+            Some(Trigger::Tag)
+        } else if self.overrides.branch.is_some() && self.ref_trigger() {
+            // The provider's pull request or tag is replaced by a branch.
+            Some(Trigger::Push)
+        } else {
+            self.provider.trigger()
+        }
+    }
+
+    fn runner(&self) -> Runner {
+        self.provider.runner()
+    }
+
+    fn parallelism(&self) -> Option<Parallelism> {
+        self.provider.parallelism()
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        if !provenance::has_value(self, field) {
+            return None;
+        }
+        let overrides = &self.overrides;
+        let name = match field {
+            "commit_sha" | "commit" if overrides.commit.is_some() => "CI_DETECTIVE_COMMIT",
+            "branch" if overrides.branch.is_some() => "CI_DETECTIVE_BRANCH",
+            "tag" if overrides.tag.is_some() => "CI_DETECTIVE_TAG",
+            "pull_request" if overrides.pull_request.is_some() => "CI_DETECTIVE_PR",
+            "git_ref" if overrides.overrides_ref() => {
+                return provenance::derived(
+                    "CI_DETECTIVE_PR, CI_DETECTIVE_TAG and CI_DETECTIVE_BRANCH",
+                )
+            }
+            "trigger" if overrides.pull_request.is_some() || overrides.tag.is_some() => {
+                return provenance::derived("CI_DETECTIVE_PR and CI_DETECTIVE_TAG")
+            }
+            "trigger" if overrides.branch.is_some() && self.ref_trigger() => {
+                return provenance::derived("CI_DETECTIVE_BRANCH")
+            }
+            _ => return self.provider.provenance(field),
+        };
+        Some(Provenance::Override(name.to_owned()))
+    }
+}
+
+impl<T: Provider> Detected<T> {
+    /// Whether the provider's trigger is what it builds: a pull request or a tag.
+    fn ref_trigger(&self) -> bool {
+        matches!(
+            self.provider.trigger(),
+            Some(Trigger::PullRequest) | Some(Trigger::Tag)
+        )
+    }
+}
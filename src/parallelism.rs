@@ -0,0 +1,16 @@
+/// Which shard of a parallel (sharded) job this is, normalized across providers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Parallelism {
+    /// The index of this shard, starting at 0.
+    pub index: usize,
+    /// The total number of shards.
+    pub total: usize,
+}
+
+impl Parallelism {
+    /// Shard `index` (starting at 0) of `total` shards.
+    pub fn new(index: usize, total: usize) -> Self {
+        Parallelism { index, total }
+    }
+}
@@ -0,0 +1,98 @@
+use build::Build;
+use provider::Provider;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Where the value of a normalized field came from.
+///
+/// See [`Provider::provenance`] for the fields that can be asked about.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Provenance {
+    /// Read from this environment variable.
+    Var(String),
+    /// Computed from these other fields or variables.
+    Derived(String),
+    /// Not reported by the provider; this crate filled in a default.
+    Default,
+    /// Mapped from a variable by this config file.
+    File(PathBuf),
+    /// Replaced through this `CI_DETECTIVE_*` variable.
+    Override(String),
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Provenance::Var(ref name) => write!(f, "${}", name),
+            Provenance::Derived(ref from) => write!(f, "derived from {}", from),
+            Provenance::Default => write!(f, "default"),
+            Provenance::File(ref path) => write!(f, "file {}", path.display()),
+            Provenance::Override(ref name) => write!(f, "overridden by ${}", name),
+        }
+    }
+}
+
+/// The fields [`Provider::provenance`] knows about, in the order the CLI shows them.
+pub const FIELDS: &[&str] = &[
+    "commit_sha",
+    "commit",
+    "branch",
+    "tag",
+    "pull_request",
+    "git_ref",
+    "trigger",
+    "build_number",
+    "build_url",
+    "build",
+    "repo_slug",
+    "repository",
+    "job_id",
+    "job",
+    "runner",
+    "parallelism",
+];
+
+pub(crate) fn var(name: &str) -> Option<Provenance> {
+    Some(Provenance::Var(name.to_owned()))
+}
+
+pub(crate) fn derived(from: &str) -> Option<Provenance> {
+    Some(Provenance::Derived(from.to_owned()))
+}
+
+/// The provenance of fields the provider computes with the default implementations of [`Provider`].
+pub(crate) fn default<P: Provider + ?Sized>(provider: &P, field: &str) -> Option<Provenance> {
+    match field {
+        "commit" if provider.commit().is_some() => derived("commit_sha"),
+        "build" if provider.build() != Build::default() => derived("build_number and build_url"),
+        "repository" if provider.repository().is_some() => derived("repo_slug"),
+        "job" if provider.job().is_some() => derived("job_id"),
+        "git_ref" => derived("pull_request, tag and branch"),
+        "trigger" if provider.trigger().is_some() => derived("git_ref"),
+        "runner" => Some(Provenance::Default),
+        _ => None,
+    }
+}
+
+/// Whether the provider has a value for `field`; fields without one have no provenance.
+pub(crate) fn has_value<P: Provider + ?Sized>(provider: &P, field: &str) -> bool {
+    match field {
+        "commit_sha" => provider.commit_sha().is_some(),
+        "commit" => provider.commit().is_some(),
+        "branch" => provider.branch().is_some(),
+        "tag" => provider.tag().is_some(),
+        "pull_request" => provider.pull_request().is_some(),
+        "trigger" => provider.trigger().is_some(),
+        "build_number" => provider.build_number().is_some(),
+        "build_url" => provider.build_url().is_some(),
+        "build" => provider.build() != Build::default(),
+        "repo_slug" => provider.repo_slug().is_some(),
+        "repository" => provider.repository().is_some(),
+        "job_id" => provider.job_id().is_some(),
+        "job" => provider.job().is_some(),
+        "parallelism" => provider.parallelism().is_some(),
+        "git_ref" | "runner" => true,
+        _ => false,
+    }
+}
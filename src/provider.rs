@@ -0,0 +1,254 @@
+use build::{Build, Job};
+use commit::Commit;
+use git_ref::GitRef;
+use parallelism::Parallelism;
+use provenance::{self, Provenance};
+use pull_request::PullRequest;
+use repository::Repository;
+use runner::Runner;
+use source::EnvSource;
+use std::fmt;
+use trigger::Trigger;
+
+/// A vendor-neutral view of the information every CI provider offers in some shape.
+///
+/// Every provider struct in this crate implements this trait, and [`CI`](crate::CI)
+/// forwards to the provider that was detected. Information a provider does not offer is `None`.
+///
+/// Other crates can implement it for their own providers and add them to detection
+/// with [`CI::register`](crate::CI::register).
+pub trait Provider: fmt::Debug + Send + Sync + CloneProvider {
+    /// The human readable name of the provider, e.g. `Travis CI`.
+    fn name(&self) -> &str;
+
+    /// The SHA of the commit being built.
+    fn commit_sha(&self) -> Option<&str> {
+        None
+    }
+
+    /// The commit being built.
+    ///
+    /// By default, this only knows the SHA from `commit_sha`.
+    fn commit(&self) -> Option<Commit> {
+        self.commit_sha().map(Commit::new)
+    }
+
+    /// The branch being built.
+    ///
+    /// For pull request builds, this is the branch the pull request comes from.
+    fn branch(&self) -> Option<&str> {
+        None
+    }
+
+    /// The tag being built.
+    fn tag(&self) -> Option<&str> {
+        None
+    }
+
+    /// The pull request being built.
+    fn pull_request(&self) -> Option<PullRequest> {
+        None
+    }
+
+    /// The number of the build, as shown by the provider.
+    fn build_number(&self) -> Option<u64> {
+        None
+    }
+
+    /// A link to the build.
+    fn build_url(&self) -> Option<&str> {
+        None
+    }
+
+    /// The build being run.
+    ///
+    /// By default, this only knows `build_number` and `build_url`.
+    fn build(&self) -> Build {
+        Build {
+            number: self.build_number(),
+            url: self.build_url().map(String::from),
+            ..Build::default()
+        }
+    }
+
+    /// The slug of the repository being built, in the form `owner/name`.
+    fn repo_slug(&self) -> Option<String> {
+        None
+    }
+
+    /// The repository being built.
+    ///
+    /// By default, this only knows the owner and name from `repo_slug`.
+    fn repository(&self) -> Option<Repository> {
+        Repository::from_slug(&self.repo_slug()?)
+    }
+
+    /// The id of the job within the build.
+    fn job_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// The job being run within the build.
+    ///
+    /// By default, this only knows `job_id`.
+    fn job(&self) -> Option<Job> {
+        Some(Job {
+            id: Some(self.job_id()?.to_owned()),
+            ..Job::default()
+        })
+    }
+
+    /// What is being built: a branch, a tag or a pull request.
+    ///
+    /// By default, this is derived from `pull_request`, `tag` and `branch`, in that order.
+    fn git_ref(&self) -> GitRef {
+        if let Some(pr) = self.pull_request() {
+            GitRef::pull_request(
+                pr.number,
+                pr.source_branch.as_deref(),
+                pr.target_branch.as_deref(),
+            )
+        } else if let Some(tag) = self.tag() {
+            GitRef::tag(tag)
+        } else if let Some(branch) = self.branch() {
+            GitRef::branch(branch)
+        } else {
+            GitRef::Detached
+        }
+    }
+
+    /// Why the build is running.
+    ///
+    /// By default, this only recognizes pull request and tag builds.
+    fn trigger(&self) -> Option<Trigger> {
+        match self.git_ref() {
+            GitRef::PullRequest { .. } => Some(Trigger::PullRequest),
+            GitRef::Tag(_) => Some(Trigger::Tag),
+            _ => None,
+        }
+    }
+
+    /// The machine the build is running on.
+    ///
+    /// By default, this only knows the operating system and architecture
+    /// this crate was compiled for.
+    fn runner(&self) -> Runner {
+        Runner::new()
+    }
+
+    /// Which shard this is, if the job is split across several machines.
+    fn parallelism(&self) -> Option<Parallelism> {
+        None
+    }
+
+    /// Where the value of a field came from, e.g. `provenance("branch")`.
+    ///
+    /// `field` is the name of one of the other methods of this trait; they are listed in
+    /// [`provenance::FIELDS`]. This is `None` if the field has
+    /// no value, or the provider doesn't know where it came from.
+    ///
+    /// By default, this only knows the fields that are derived from other fields.
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        provenance::default(self, field)
+    }
+}
+
+/// Cloning a boxed [`Provider`], so [`CI::Custom`](crate::CI::Custom) can be cloned.
+///
+/// This is implemented for every provider that is `Clone`.
+pub trait CloneProvider {
+    /// Clone this provider into a new box.
+    fn clone_provider(&self) -> Box<dyn Provider>;
+}
+
+impl<T: Provider + Clone + 'static> CloneProvider for T {
+    fn clone_provider(&self) -> Box<dyn Provider> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Provider> {
+    fn clone(&self) -> Self {
+        (**self).clone_provider()
+    }
+}
+
+/// Forwards to the boxed provider.
+impl Provider for Box<dyn Provider> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        (**self).commit_sha()
+    }
+
+    fn commit(&self) -> Option<Commit> {
+        (**self).commit()
+    }
+
+    fn branch(&self) -> Option<&str> {
+        (**self).branch()
+    }
+
+    fn tag(&self) -> Option<&str> {
+        (**self).tag()
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        (**self).pull_request()
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        (**self).build_number()
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        (**self).build_url()
+    }
+
+    fn build(&self) -> Build {
+        (**self).build()
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        (**self).repo_slug()
+    }
+
+    fn repository(&self) -> Option<Repository> {
+        (**self).repository()
+    }
+
+    fn job_id(&self) -> Option<&str> {
+        (**self).job_id()
+    }
+
+    fn job(&self) -> Option<Job> {
+        (**self).job()
+    }
+
+    fn git_ref(&self) -> GitRef {
+        (**self).git_ref()
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        (**self).trigger()
+    }
+
+    fn runner(&self) -> Runner {
+        (**self).runner()
+    }
+
+    fn parallelism(&self) -> Option<Parallelism> {
+        (**self).parallelism()
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        (**self).provenance(field)
+    }
+}
+
+/// Detects a provider registered with [`CI::register`](crate::CI::register).
+///
+/// This returns `None` if the variables don't identify the provider.
+pub type CustomDetector = fn(&dyn EnvSource) -> Option<Box<dyn Provider>>;
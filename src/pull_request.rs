@@ -0,0 +1,42 @@
+/// A pull request being built, normalized across providers.
+///
+/// Providers fill in as much as they know; everything but the number is optional.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct PullRequest {
+    /// The number of the pull request.
+    pub number: u64,
+    /// A link to the pull request.
+    pub url: Option<String>,
+    /// The title of the pull request.
+    pub title: Option<String>,
+    /// The branch the pull request comes from.
+    pub source_branch: Option<String>,
+    /// The branch the pull request wants to merge into.
+    pub target_branch: Option<String>,
+    /// The SHA of the newest commit of the pull request.
+    pub head_sha: Option<String>,
+    /// The SHA of the commit the pull request is based on.
+    pub base_sha: Option<String>,
+    /// The slug (`owner/name`) of the repository the pull request comes from.
+    pub head_repo: Option<String>,
+    /// Whether the pull request comes from a fork of the repository being built.
+    pub is_fork: Option<bool>,
+}
+
+impl PullRequest {
+    /// A pull request of which only the number is known.
+    pub fn new(number: u64) -> Self {
+        PullRequest {
+            number,
+            url: None,
+            title: None,
+            source_branch: None,
+            target_branch: None,
+            head_sha: None,
+            base_sha: None,
+            head_repo: None,
+            is_fork: None,
+        }
+    }
+}
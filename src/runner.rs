@@ -0,0 +1,51 @@
+use std::env::consts;
+
+/// The machine the build is running on, normalized across providers.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Runner {
+    /// The operating system, in the format of `std::env::consts::OS`, e.g. `linux` or `macos`.
+    pub os: String,
+    /// The CPU architecture, in the format of `std::env::consts::ARCH`, e.g. `x86_64`.
+    pub arch: String,
+    /// The image the runner was started from, e.g. `xcode10.1` or `Visual Studio 2017`.
+    pub image: Option<String>,
+    /// The name of the runner.
+    pub name: Option<String>,
+    /// The executor on the runner, for runners that run several builds at once.
+    pub executor: Option<String>,
+    /// Whether the provider or the project owns the runner.
+    pub hosting: Option<Hosting>,
+}
+
+impl Runner {
+    /// A runner of which nothing is known.
+    ///
+    /// The operating system and architecture are those this crate was compiled for.
+    pub fn new() -> Self {
+        Runner {
+            os: consts::OS.to_owned(),
+            arch: consts::ARCH.to_owned(),
+            image: None,
+            name: None,
+            executor: None,
+            hosting: None,
+        }
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Runner::new()
+    }
+}
+
+/// Who owns the machine the build is running on.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Hosting {
+    /// The CI provider runs the machine.
+    Hosted,
+    /// The project runs the machine.
+    SelfHosted,
+}
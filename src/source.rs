@@ -0,0 +1,183 @@
+use error::Warning;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::hash::{BuildHasher, Hash};
+use std::iter::FromIterator;
+#[cfg(feature = "config")]
+use std::sync::OnceLock;
+#[cfg(feature = "config")]
+use Config;
+
+/// Where detection reads environment variables from.
+///
+/// The process environment is [`ProcessEnv`]; maps, lists of pairs and closures
+/// make it possible to detect a provider from any set of variables, e.g. in tests.
+/// To read from an iterator of pairs, collect it into a [`Snapshot`] first.
+///
+/// What a variable set to the empty string means depends on the variable: most are
+/// treated as unset, some keep the empty value because it carries meaning,
+/// like `TRAVIS_COMMIT_RANGE` on the first build of a branch, and some report it as invalid.
+pub trait EnvSource {
+    /// The value of the variable `name`, if it is set.
+    fn var_os(&self, name: &str) -> Option<OsString>;
+
+    /// The snapshot behind this source, whose loaded config can be reused.
+    #[doc(hidden)]
+    fn snapshot(&self) -> Option<&Snapshot> {
+        None
+    }
+}
+
+/// The value of a variable, if it is set to something other than the empty string.
+///
+/// A value that is not valid Unicode is added to `warnings`, and `None` is returned.
+pub(crate) fn var(
+    source: &dyn EnvSource,
+    name: &str,
+    warnings: &mut Vec<Warning>,
+) -> Option<String> {
+    match source.var_os(name)?.into_string() {
+        Ok(ref value) if value.is_empty() => None,
+        Ok(value) => Some(value),
+        Err(_) => {
+            warnings.push(Warning::NotUnicode(name.to_owned()));
+            None
+        }
+    }
+}
+
+/// The environment of the current process.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ProcessEnv;
+
+impl EnvSource for ProcessEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        env::var_os(name)
+    }
+}
+
+/// A copy of a set of variables, taken once and then read without touching the environment again.
+///
+/// This can also be collected from an iterator of pairs.
+///
+/// With the `config` feature, the config file is loaded the first time a snapshot is
+/// detected from, and reused by every later detection from the same snapshot.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    vars: HashMap<String, OsString>,
+    #[cfg(feature = "config")]
+    config: OnceLock<Result<Option<Config>, String>>,
+}
+
+impl Snapshot {
+    /// Copy the environment of the current process.
+    ///
+    /// Variables whose names are not valid Unicode can't be looked up, so they are left out.
+    pub fn capture() -> Self {
+        env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+            .collect()
+    }
+
+    /// The config these variables point to, loaded once.
+    #[cfg(feature = "config")]
+    pub(crate) fn config(&self) -> &Result<Option<Config>, String> {
+        self.config
+            .get_or_init(|| Config::load_from(self).map_err(|err| err.to_string()))
+    }
+}
+
+impl PartialEq for Snapshot {
+    fn eq(&self, other: &Self) -> bool {
+        self.vars == other.vars
+    }
+}
+
+impl Eq for Snapshot {}
+
+impl<K: Into<String>, V: Into<OsString>> FromIterator<(K, V)> for Snapshot {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Snapshot {
+            vars: iter
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+            #[cfg(feature = "config")]
+            config: OnceLock::new(),
+        }
+    }
+}
+
+impl EnvSource for Snapshot {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.vars.get(name).cloned()
+    }
+
+    fn snapshot(&self) -> Option<&Snapshot> {
+        Some(self)
+    }
+}
+
+/// Makes any source, even a slice, usable as `&dyn EnvSource`.
+pub(crate) struct ByRef<'a, S: EnvSource + ?Sized + 'a>(pub(crate) &'a S);
+
+impl<'a, S: EnvSource + ?Sized> EnvSource for ByRef<'a, S> {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.0.var_os(name)
+    }
+
+    fn snapshot(&self) -> Option<&Snapshot> {
+        self.0.snapshot()
+    }
+}
+
+impl<K, V, S> EnvSource for HashMap<K, V, S>
+where
+    K: Eq + Hash + Borrow<str>,
+    V: AsRef<OsStr>,
+    S: BuildHasher,
+{
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.get(name).map(|it| it.as_ref().to_owned())
+    }
+}
+
+impl<K, V> EnvSource for BTreeMap<K, V>
+where
+    K: Ord + Borrow<str>,
+    V: AsRef<OsStr>,
+{
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.get(name).map(|it| it.as_ref().to_owned())
+    }
+}
+
+/// The last pair with the given name wins, like it would when setting the variables in order.
+impl<K: AsRef<str>, V: AsRef<OsStr>> EnvSource for [(K, V)] {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.iter()
+            .rev()
+            .find(|(key, _)| key.as_ref() == name)
+            .map(|(_, value)| value.as_ref().to_owned())
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<OsStr>, const N: usize> EnvSource for [(K, V); N] {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self[..].var_os(name)
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<OsStr>> EnvSource for Vec<(K, V)> {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self[..].var_os(name)
+    }
+}
+
+impl<F: Fn(&str) -> Option<String>> EnvSource for F {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self(name).map(OsString::from)
+    }
+}
@@ -1,6 +1,13 @@
-use env;
+use build::{Build, Job};
+use commit::Commit;
+use provenance::{self, Provenance};
+use provider::Provider;
+use pull_request::PullRequest;
+use runner::{Hosting, Runner};
 use std::path::PathBuf;
 use std::str::FromStr;
+use trigger::Trigger;
+use vars::{Detect, Empty, Missing, Placeholder, Vars};
 
 /// Travis CI
 ///
@@ -9,7 +16,7 @@ use std::str::FromStr;
 /// - <https://docs.travis-ci.com/user/environment-variables/#Default-Environment-Variables>
 /// - <https://github.com/codecov/codecov-bash/blob/8b76995ad4a95a61cecd4b049a448a402d91d197/codecov#L467-L489>
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[non_exhaustive]
 pub struct Travis {
     /// - set to `true` if the job is allowed to fail.
     /// - set to `false` if the job is not allowed to fail.
@@ -28,6 +35,8 @@ pub struct Travis {
     pub build_dir: PathBuf,
     /// The id of the current build that Travis CI uses internally.
     pub build_id: String,
+    /// URL to the build log.
+    pub build_web_url: Option<String>,
     /// The number of the current build (for example, `4`).
     pub build_number: usize,
     /// The commit that the current build is testing.
@@ -43,10 +52,11 @@ pub struct Travis {
     pub job_id: String,
     /// The number of the current job (for example, `4.1`).
     pub job_number: String,
+    /// URL to the job log.
+    pub job_web_url: Option<String>,
     /// On multi-OS builds, this value indicates the platform the job is running on.
     pub os: Option<OS>,
-    /// The `osx_image` value configured in `.travis.yml`.
-    /// If this is not set in `.travis.yml`, it is emtpy.
+    /// The `osx_image` value configured in `.travis.yml`, if it is set there.
     pub osx_image: Option<String>,
     /// The pull request number if the current job is a pull request.
     pub pull_request: Option<String>,
@@ -102,85 +112,225 @@ pub struct Travis {
     pub xcode_project: Option<String>,
     /// The current XCode Workspace being used to run the build (if any).
     pub xcode_workspace: Option<String>,
-    non_exhaustive: (),
+    /// The required variables that were missing or invalid, in lenient detection.
+    missing: Missing,
 }
 
-impl Travis {
-    /// Construct this provider's information from the environment.
-    pub fn from_env() -> Option<Self> {
-        if !(env("CI")? == "true"
-            && env("TRAVIS")? == "true"
-            && env("CONTINUOUS_INTEGRATION")? == "true"
-            && env("DEBIAN_FRONTEND")? == "noninteractive"
-            && env("HAS_JOSH_K_SEAL_OF_APPROVAL")? == "true")
-        {
+impl_from_env!(Travis);
+
+impl Detect for Travis {
+    const NAME: &'static str = "Travis CI";
+
+    fn read(vars: &mut Vars) -> Option<Self> {
+        if !vars.markers(&[
+            ("CI", Some("true")),
+            ("TRAVIS", Some("true")),
+            ("CONTINUOUS_INTEGRATION", Some("true")),
+            ("DEBIAN_FRONTEND", Some("noninteractive")),
+            ("HAS_JOSH_K_SEAL_OF_APPROVAL", Some("true")),
+        ]) {
             return None;
         }
 
         Some(Travis {
-            allow_failure: env("TRAVIS_ALLOW_FAILURE")?.parse().ok()?,
-            branch: env("TRAVIS_BRANCH")?,
-            build_dir: env("TRAVIS_BUILD_DIR")?.into(),
-            build_id: env("TRAVIS_BUILD_ID")?,
-            build_number: env("TRAVIS_BUILD_NUMBER")?.parse().ok()?,
-            commit: env("TRAVIS_COMMIT")?,
-            commit_message: env("TRAVIS_COMMIT_MESSAGE")?,
-            commit_range: env("TRAVIS_COMMIT_RANGE"),
-            event_type: env("TRAVIS_EVENT_TYPE")?.parse().ok()?,
-            job_id: env("TRAVIS_JOB_ID")?,
-            job_number: env("TRAVIS_JOB_NUMBER")?,
-            os: env("TRAVIS_OS_NAME").and_then(|it| it.parse().ok()),
-            osx_image: env("TRAVIS_OSX_IMAGE"),
-            pull_request: if let Some(pr) = env("TRAVIS_PULL_REQUEST") {
-                if pr != "false" {
-                    Some(pr)
-                } else {
-                    None
-                }
-            } else {
-                None
-            },
-            pull_request_branch: env("TRAVIS_PULL_REQUEST_BRANCH"),
-            pull_request_sha: env("TRAVIS_PULL_REQUEST_SHA"),
-            pull_request_slug: env("TRAVIS_PULL_REQUEST_SLUG"),
-            repo_slug: env("TRAVIS_REPO_SLUG")?,
-            secure_env_vars: env("TRAVIS_SECURE_ENV_VARS")?.parse().ok()?,
-            sudo: env("TRAVIS_SUDO")?.parse().ok()?,
-            tag: env("TRAVIS_TAG"),
-            dart_version: env("TRAVIS_DART_VERSION"),
-            go_version: env("TRAVIS_GO_VERSION"),
-            haxe_version: env("TRAVIS_HAXE_VERSION"),
-            jdk_version: env("TRAVIS_JDK_VESRION"),
-            julia_version: env("TRAVIS_JULIA_VERSION"),
-            node_version: env("TRAVIS_NODE_VERSION"),
-            otp_release: env("TRAVIS_OTP_RELEASE"),
-            perl_version: env("TRAVIS_PERL_VERSION"),
-            php_version: env("TRAVIS_PHP_VERSION"),
-            python_version: env("TRAVIS_PYTHON_VERSION"),
-            r_version: env("TRAVIS_R_VERSION"),
-            ruby_version: env("TRAVIS_RUBY_VERSION"),
-            rust_version: env("TRAVIS_RUST_VERSION"),
-            scala_version: env("TRAVIS_SCALA_VERSION"),
-            xcode_sdk: env("TRAVIS_XCODE_SDK"),
-            xcode_scheme: env("TRAVIS_XCODE_SCHEME"),
-            xcode_project: env("TRAVIS_XCODE_PROJECT"),
-            xcode_workspace: env("TRAVIS_XCODE_WORKSPACE"),
-            non_exhaustive: (),
+            allow_failure: vars.required("TRAVIS_ALLOW_FAILURE"),
+            branch: vars.required("TRAVIS_BRANCH"),
+            build_dir: vars.required_path("TRAVIS_BUILD_DIR"),
+            build_id: vars.required("TRAVIS_BUILD_ID"),
+            build_web_url: vars.optional("TRAVIS_BUILD_WEB_URL"),
+            build_number: vars.required("TRAVIS_BUILD_NUMBER"),
+            commit: vars.required("TRAVIS_COMMIT"),
+            commit_message: vars.required("TRAVIS_COMMIT_MESSAGE"),
+            commit_range: vars.optional_with(Empty::Present, "TRAVIS_COMMIT_RANGE"),
+            event_type: vars.required("TRAVIS_EVENT_TYPE"),
+            job_id: vars.required("TRAVIS_JOB_ID"),
+            job_number: vars.required("TRAVIS_JOB_NUMBER"),
+            job_web_url: vars.optional("TRAVIS_JOB_WEB_URL"),
+            os: vars.optional("TRAVIS_OS_NAME"),
+            osx_image: vars.optional("TRAVIS_OSX_IMAGE"),
+            pull_request: vars
+                .optional_with(Empty::Invalid, "TRAVIS_PULL_REQUEST")
+                .filter(|pr| pr != "false"),
+            pull_request_branch: vars.optional("TRAVIS_PULL_REQUEST_BRANCH"),
+            pull_request_sha: vars.optional("TRAVIS_PULL_REQUEST_SHA"),
+            pull_request_slug: vars.optional("TRAVIS_PULL_REQUEST_SLUG"),
+            repo_slug: vars.required("TRAVIS_REPO_SLUG"),
+            secure_env_vars: vars.required("TRAVIS_SECURE_ENV_VARS"),
+            sudo: vars.required("TRAVIS_SUDO"),
+            tag: vars.optional("TRAVIS_TAG"),
+            dart_version: vars.optional("TRAVIS_DART_VERSION"),
+            go_version: vars.optional("TRAVIS_GO_VERSION"),
+            haxe_version: vars.optional("TRAVIS_HAXE_VERSION"),
+            jdk_version: vars.optional("TRAVIS_JDK_VESRION"),
+            julia_version: vars.optional("TRAVIS_JULIA_VERSION"),
+            node_version: vars.optional("TRAVIS_NODE_VERSION"),
+            otp_release: vars.optional("TRAVIS_OTP_RELEASE"),
+            perl_version: vars.optional("TRAVIS_PERL_VERSION"),
+            php_version: vars.optional("TRAVIS_PHP_VERSION"),
+            python_version: vars.optional("TRAVIS_PYTHON_VERSION"),
+            r_version: vars.optional("TRAVIS_R_VERSION"),
+            ruby_version: vars.optional("TRAVIS_RUBY_VERSION"),
+            rust_version: vars.optional("TRAVIS_RUST_VERSION"),
+            scala_version: vars.optional("TRAVIS_SCALA_VERSION"),
+            xcode_sdk: vars.optional("TRAVIS_XCODE_SDK"),
+            xcode_scheme: vars.optional("TRAVIS_XCODE_SCHEME"),
+            xcode_project: vars.optional("TRAVIS_XCODE_PROJECT"),
+            xcode_workspace: vars.optional("TRAVIS_XCODE_WORKSPACE"),
+            missing: vars.missing(),
         })
     }
 }
 
+impl Provider for Travis {
+    fn name(&self) -> &str {
+        Travis::NAME
+    }
+
+    fn commit_sha(&self) -> Option<&str> {
+        self.missing.given("TRAVIS_COMMIT", &*self.commit)
+    }
+
+    fn commit(&self) -> Option<Commit> {
+        let mut commit = Commit::new(self.commit_sha()?);
+        commit.set_message(&self.commit_message);
+        Some(commit)
+    }
+
+    fn branch(&self) -> Option<&str> {
+        if self.tag.is_some() {
+            None
+        } else if self.pull_request.is_some() {
+            self.pull_request_branch.as_deref()
+        } else {
+            self.missing.given("TRAVIS_BRANCH", &*self.branch)
+        }
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    fn pull_request(&self) -> Option<PullRequest> {
+        let mut pr = PullRequest::new(self.pull_request.as_ref()?.parse().ok()?);
+        pr.source_branch = self.pull_request_branch.clone();
+        pr.target_branch = self.missing.given("TRAVIS_BRANCH", self.branch.clone());
+        pr.head_sha = self.pull_request_sha.clone();
+        pr.base_sha = self
+            .commit_range
+            .as_ref()
+            .and_then(|range| range.find("...").map(|end| range[..end].to_owned()));
+        pr.head_repo = self.pull_request_slug.clone();
+        pr.is_fork = self
+            .pull_request_slug
+            .as_ref()
+            .filter(|_| !self.missing.contains("TRAVIS_REPO_SLUG"))
+            .map(|slug| *slug != self.repo_slug);
+        Some(pr)
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.missing
+            .given("TRAVIS_BUILD_NUMBER", self.build_number as u64)
+    }
+
+    fn repo_slug(&self) -> Option<String> {
+        self.missing
+            .given("TRAVIS_REPO_SLUG", self.repo_slug.clone())
+    }
+
+    fn job_id(&self) -> Option<&str> {
+        self.missing.given("TRAVIS_JOB_ID", &*self.job_id)
+    }
+
+    fn build_url(&self) -> Option<&str> {
+        self.build_web_url.as_deref()
+    }
+
+    fn build(&self) -> Build {
+        Build {
+            id: self.missing.given("TRAVIS_BUILD_ID", self.build_id.clone()),
+            number: self.build_number(),
+            url: self.build_web_url.clone(),
+            ..Build::default()
+        }
+    }
+
+    fn job(&self) -> Option<Job> {
+        let job = Job {
+            id: self.job_id().map(str::to_owned),
+            number: self
+                .missing
+                .given("TRAVIS_JOB_NUMBER", self.job_number.clone()),
+            url: self.job_web_url.clone(),
+            ..Job::default()
+        };
+        Some(job).filter(|job| *job != Job::default())
+    }
+
+    fn trigger(&self) -> Option<Trigger> {
+        match *self.missing.given("TRAVIS_EVENT_TYPE", &self.event_type)? {
+            EventType::Push if self.tag.is_some() => Some(Trigger::Tag),
+            EventType::Push => Some(Trigger::Push),
+            EventType::PullRequest => Some(Trigger::PullRequest),
+            EventType::Api => Some(Trigger::Api),
+            EventType::Cron => Some(Trigger::Schedule),
+        }
+    }
+
+    fn runner(&self) -> Runner {
+        let mut runner = Runner::new();
+        match self.os {
+            Some(OS::Linux) => runner.os = "linux".to_owned(),
+            Some(OS::MacOS) => runner.os = "macos".to_owned(),
+            None => {}
+        }
+        runner.image = self.osx_image.clone();
+        runner.hosting = Some(Hosting::Hosted);
+        runner
+    }
+
+    fn provenance(&self, field: &str) -> Option<Provenance> {
+        if !provenance::has_value(self, field) {
+            return None;
+        }
+        match field {
+            "commit_sha" => provenance::var("TRAVIS_COMMIT"),
+            "commit" => provenance::derived("TRAVIS_COMMIT and TRAVIS_COMMIT_MESSAGE"),
+            "branch" if self.branch().is_none() => None,
+            "branch" if self.pull_request.is_some() => {
+                provenance::var("TRAVIS_PULL_REQUEST_BRANCH")
+            }
+            "branch" => provenance::var("TRAVIS_BRANCH"),
+            "tag" if self.tag.is_some() => provenance::var("TRAVIS_TAG"),
+            "pull_request" if self.pull_request().is_some() => {
+                provenance::var("TRAVIS_PULL_REQUEST")
+            }
+            "trigger" => provenance::var("TRAVIS_EVENT_TYPE"),
+            "build_number" => provenance::var("TRAVIS_BUILD_NUMBER"),
+            "build_url" if self.build_web_url.is_some() => provenance::var("TRAVIS_BUILD_WEB_URL"),
+            "build" => {
+                provenance::derived("TRAVIS_BUILD_ID, TRAVIS_BUILD_NUMBER and TRAVIS_BUILD_WEB_URL")
+            }
+            "repo_slug" => provenance::var("TRAVIS_REPO_SLUG"),
+            "job_id" => provenance::var("TRAVIS_JOB_ID"),
+            "job" => provenance::derived("TRAVIS_JOB_ID, TRAVIS_JOB_NUMBER and TRAVIS_JOB_WEB_URL"),
+            "runner" if self.os.is_some() || self.runner().image.is_some() => {
+                provenance::derived("TRAVIS_OS_NAME and TRAVIS_OSX_IMAGE")
+            }
+            _ => provenance::default(self, field),
+        }
+    }
+}
+
 /// Indicates how the build was triggered.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[non_exhaustive]
 #[allow(missing_docs)]
 pub enum EventType {
     Push,
     PullRequest,
     Api,
     Cron,
-    #[doc(hidden)]
-    __NonExhaustive,
 }
 
 impl FromStr for EventType {
@@ -196,16 +346,20 @@ impl FromStr for EventType {
     }
 }
 
+impl Placeholder for EventType {
+    fn placeholder() -> Self {
+        EventType::Push
+    }
+}
+
 /// On multi-OS builds, this value indicates the platform the job is running on.
 /// To be extended in the future.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-#[cfg_attr(feature = "nightly", non_exhaustive)]
+#[non_exhaustive]
 #[allow(missing_docs)]
 pub enum OS {
     Linux,
     MacOS,
-    #[doc(hidden)]
-    __NonExhaustive,
 }
 
 impl FromStr for OS {
@@ -0,0 +1,35 @@
+use git_ref::GitRef;
+
+/// Why the build is running, normalized across providers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Trigger {
+    /// Commits were pushed to a branch.
+    Push,
+    /// A pull request was opened or updated.
+    PullRequest,
+    /// A tag was pushed.
+    Tag,
+    /// The build runs on a schedule, e.g. a nightly cron job.
+    Schedule,
+    /// Somebody started the build by hand.
+    Manual,
+    /// The build was started through the provider's API.
+    Api,
+    /// Another pipeline started the build.
+    Upstream,
+    /// A previous build is being run again.
+    Rebuild,
+}
+
+impl Trigger {
+    /// The trigger of a build on a provider that only builds what is pushed to it.
+    pub(crate) fn of_push(git_ref: &GitRef) -> Option<Trigger> {
+        match *git_ref {
+            GitRef::Branch(_) => Some(Trigger::Push),
+            GitRef::Tag(_) => Some(Trigger::Tag),
+            GitRef::PullRequest { .. } => Some(Trigger::PullRequest),
+            GitRef::Detached => None,
+        }
+    }
+}
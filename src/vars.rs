@@ -0,0 +1,400 @@
+use error::{Candidate, InvalidVar, Partial, Warning};
+use source::EnvSource;
+use std::any::type_name;
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A provider that can be detected from environment variables.
+pub(crate) trait Detect: Sized {
+    /// The name of the provider, as reported by `Provider::name`.
+    const NAME: &'static str;
+
+    /// Check the marker variables and read the provider's variables.
+    ///
+    /// Returns `None` if the markers don't identify this provider.
+    fn read(vars: &mut Vars) -> Option<Self>;
+}
+
+/// Detect `T`, requiring all of its marker and required variables to be valid.
+pub(crate) fn detect<T: Detect>(source: &dyn EnvSource) -> Result<T, Candidate> {
+    let mut vars = Vars::new(source, T::NAME, false);
+    let value = T::read(&mut vars);
+    vars.finish(value)
+}
+
+/// Detect `T` as soon as its markers identify it, turning every other problem into a warning.
+pub(crate) fn detect_lenient<T: Detect>(source: &dyn EnvSource) -> Option<Partial<T>> {
+    let mut vars = Vars::new(source, T::NAME, true);
+    let provider = T::read(&mut vars)?;
+    let confidence = vars.confidence();
+    Some(Partial {
+        provider,
+        confidence,
+        warnings: vars.into_warnings(),
+    })
+}
+
+/// The constructors of a provider that implements [`Detect`], which are the same for all of them.
+macro_rules! impl_from_env {
+    ($provider:ident) => {
+        impl $provider {
+            /// Construct this provider's information from the environment.
+            pub fn from_env() -> Option<Self> {
+                $provider::from_source(&$crate::source::ProcessEnv)
+            }
+
+            /// Construct this provider's information from the environment,
+            /// explaining what is missing or invalid if that is not possible.
+            pub fn try_from_env() -> Result<Self, $crate::error::DetectError> {
+                $provider::try_from_source(&$crate::source::ProcessEnv)
+            }
+
+            /// Construct this provider's information from the environment as soon as its
+            /// marker variables are found, listing what is missing or invalid as warnings.
+            pub fn from_env_lenient() -> Option<$crate::error::Partial<Self>> {
+                $provider::from_source_lenient(&$crate::source::ProcessEnv)
+            }
+
+            /// Construct this provider's information from the given variables.
+            pub fn from_source<S: $crate::source::EnvSource + ?Sized>(source: &S) -> Option<Self> {
+                $provider::try_from_source(source).ok()
+            }
+
+            /// Like [`try_from_env`](Self::try_from_env), but reading the given variables.
+            pub fn try_from_source<S: $crate::source::EnvSource + ?Sized>(
+                source: &S,
+            ) -> Result<Self, $crate::error::DetectError> {
+                $crate::vars::detect(&$crate::source::ByRef(source))
+                    .map_err($crate::error::DetectError::from)
+            }
+
+            /// Like [`from_env_lenient`](Self::from_env_lenient), but reading the given variables.
+            pub fn from_source_lenient<S: $crate::source::EnvSource + ?Sized>(
+                source: &S,
+            ) -> Option<$crate::error::Partial<Self>> {
+                $crate::vars::detect_lenient(&$crate::source::ByRef(source))
+            }
+        }
+    };
+}
+
+/// Reads environment variables for a provider, recording everything that is missing or invalid.
+///
+/// Required values that are missing or invalid are replaced by a placeholder,
+/// so reading can go on and find all problems at once; [`Vars::missing`] lists them.
+pub(crate) struct Vars<'a> {
+    source: &'a dyn EnvSource,
+    candidate: Candidate,
+    lenient: bool,
+    /// How many marker and required variables were checked.
+    checked: usize,
+    /// Problems that don't make strict detection fail.
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Vars<'a> {
+    fn new(source: &'a dyn EnvSource, provider: &'static str, lenient: bool) -> Self {
+        Vars {
+            source,
+            candidate: Candidate::new(provider),
+            lenient,
+            checked: 0,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn finish<T>(self, value: Option<T>) -> Result<T, Candidate> {
+        match value {
+            Some(value) if self.failed() == 0 => Ok(value),
+            _ => Err(self.candidate),
+        }
+    }
+
+    /// How many marker and required variables are missing or invalid.
+    fn failed(&self) -> usize {
+        self.candidate.missing.len()
+            + self.candidate.invalid.len()
+            + self.candidate.not_unicode.len()
+    }
+
+    /// The share of checked marker and required variables that are set and valid.
+    fn confidence(&self) -> f64 {
+        if self.checked == 0 {
+            return 0.0;
+        }
+        (self.checked - self.failed()) as f64 / self.checked as f64
+    }
+
+    fn into_warnings(self) -> Vec<Warning> {
+        let missing = self.candidate.missing.into_iter().map(Warning::Missing);
+        let invalid = self.candidate.invalid.into_iter().map(Warning::Invalid);
+        let not_unicode = self
+            .candidate
+            .not_unicode
+            .into_iter()
+            .map(Warning::NotUnicode);
+        missing
+            .chain(invalid)
+            .chain(not_unicode)
+            .chain(self.warnings)
+            .collect()
+    }
+
+    /// The required variables read so far that were missing or invalid.
+    ///
+    /// Read this after all other variables, as the last field of the provider.
+    pub(crate) fn missing(&self) -> Missing {
+        let candidate = &self.candidate;
+        let invalid = candidate.invalid.iter().map(|invalid| &invalid.name);
+        let names = candidate
+            .missing
+            .iter()
+            .chain(invalid)
+            .chain(&candidate.not_unicode);
+        Missing(names.cloned().collect())
+    }
+
+    /// Read a group of variables that is only there some of the time, like a plugin's.
+    ///
+    /// In lenient mode, the group's problems become warnings of this provider.
+    pub(crate) fn group<T: Detect>(&mut self) -> Option<T> {
+        let mut vars = Vars::new(self.source, T::NAME, self.lenient);
+        let value = T::read(&mut vars);
+        if self.lenient {
+            let value = value?;
+            self.warnings.extend(vars.into_warnings());
+            Some(value)
+        } else {
+            vars.finish(value).ok()
+        }
+    }
+
+    /// The value of a variable, if it is set to something other than the empty string.
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.source.var_os(name).filter(|value| !value.is_empty())
+    }
+
+    /// A value as a string.
+    ///
+    /// A value that is not valid Unicode is recorded as such, and `None` is returned.
+    fn string(&mut self, required: bool, name: &str, value: OsString) -> Option<String> {
+        match value.into_string() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                if required {
+                    self.candidate.not_unicode.push(name.to_owned());
+                } else {
+                    self.warnings.push(Warning::NotUnicode(name.to_owned()));
+                }
+                None
+            }
+        }
+    }
+
+    fn invalid(&mut self, required: bool, invalid: InvalidVar) {
+        if required {
+            self.candidate.invalid.push(invalid);
+        } else {
+            self.warnings.push(Warning::Invalid(invalid));
+        }
+    }
+
+    /// Check marker variables, which must all be set (to `expected`, if given).
+    pub(crate) fn markers(&mut self, markers: &[(&str, Option<&str>)]) -> bool {
+        let mut found = true;
+        for &(name, expected) in markers {
+            self.checked += 1;
+            if !self.is_set(name) {
+                self.candidate.missing.push(name.to_owned());
+                found = false;
+                continue;
+            }
+            // Without an expected value, any value will do, even one that is not valid Unicode.
+            if let Some(expected) = expected {
+                let value = self.var_os(name).unwrap_or_default();
+                let value = match self.string(true, name, value) {
+                    Some(value) => value,
+                    None => {
+                        found = false;
+                        continue;
+                    }
+                };
+                if value != expected {
+                    self.candidate.invalid.push(InvalidVar {
+                        name: name.to_owned(),
+                        value,
+                        expected: format!("`{}`", expected),
+                    });
+                    found = false;
+                    continue;
+                }
+            }
+            self.candidate.markers_found.push(name.to_owned());
+        }
+        self.candidate.identified = found;
+        found
+    }
+
+    /// Check marker variables of which any one is enough.
+    ///
+    /// These don't count towards the confidence, since they are only a hint.
+    pub(crate) fn any_marker(&mut self, markers: &[&str]) -> bool {
+        for &name in markers {
+            if self.is_set(name) {
+                self.candidate.markers_found.push(name.to_owned());
+            }
+        }
+        self.candidate.identified = !self.candidate.markers_found.is_empty();
+        self.candidate.identified
+    }
+
+    /// Whether the variable is set.
+    pub(crate) fn is_set(&self, name: &str) -> bool {
+        self.var_os(name).is_some()
+    }
+
+    /// Read a variable that must be set.
+    pub(crate) fn required<T: FromStr + Placeholder>(&mut self, name: &str) -> T {
+        self.required_if(true, name)
+            .unwrap_or_else(Placeholder::placeholder)
+    }
+
+    /// Read a variable that must be set if `required` is true.
+    pub(crate) fn required_if<T: FromStr>(&mut self, required: bool, name: &str) -> Option<T> {
+        self.read(required, Empty::Missing, name)
+    }
+
+    /// Read a variable that must be set, where `empty` says what an empty value means.
+    pub(crate) fn required_with<T: FromStr + Placeholder>(
+        &mut self,
+        empty: Empty,
+        name: &str,
+    ) -> T {
+        self.read(true, empty, name)
+            .unwrap_or_else(Placeholder::placeholder)
+    }
+
+    /// Read a variable that may be unset, where `empty` says what an empty value means.
+    pub(crate) fn optional_with<T: FromStr>(&mut self, empty: Empty, name: &str) -> Option<T> {
+        self.read(false, empty, name)
+    }
+
+    fn read<T: FromStr>(&mut self, required: bool, empty: Empty, name: &str) -> Option<T> {
+        if required {
+            self.checked += 1;
+        }
+        let value = match self.source.var_os(name) {
+            Some(ref value) if value.is_empty() && empty == Empty::Invalid => {
+                let invalid = InvalidVar {
+                    name: name.to_owned(),
+                    value: String::new(),
+                    expected: "allowed to be empty".to_owned(),
+                };
+                self.invalid(required, invalid);
+                return None;
+            }
+            Some(ref value) if value.is_empty() && empty == Empty::Missing => None,
+            value => value,
+        };
+        let value = match value {
+            Some(value) => self.string(required, name, value)?,
+            None => {
+                if required {
+                    self.candidate.missing.push(name.to_owned());
+                }
+                return None;
+            }
+        };
+        match value.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                let invalid = InvalidVar {
+                    name: name.to_owned(),
+                    value,
+                    expected: short_type_name::<T>().to_owned(),
+                };
+                self.invalid(required, invalid);
+                None
+            }
+        }
+    }
+
+    /// Read the first of several variables that is set, and which one it was.
+    pub(crate) fn first_of<T: FromStr>(
+        &mut self,
+        names: &[&'static str],
+    ) -> Option<(T, &'static str)> {
+        names
+            .iter()
+            .find_map(|&name| self.optional(name).map(|value| (value, name)))
+    }
+
+    /// Read a variable that may be unset.
+    pub(crate) fn optional<T: FromStr>(&mut self, name: &str) -> Option<T> {
+        self.required_if(false, name)
+    }
+
+    /// Read a path that must be set, keeping it as is even if it is not valid Unicode.
+    pub(crate) fn required_path(&mut self, name: &str) -> PathBuf {
+        self.checked += 1;
+        self.optional_path(name).unwrap_or_else(|| {
+            self.candidate.missing.push(name.to_owned());
+            PathBuf::new()
+        })
+    }
+
+    /// Read a path that may be unset, keeping it as is even if it is not valid Unicode.
+    pub(crate) fn optional_path(&mut self, name: &str) -> Option<PathBuf> {
+        self.var_os(name).map(PathBuf::from)
+    }
+}
+
+/// What an empty variable means.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Empty {
+    /// The same as an unset variable; what most variables want.
+    Missing,
+    /// A value like any other, for variables where empty carries meaning.
+    Present,
+    /// A mistake, reported like an invalid value.
+    Invalid,
+}
+
+fn short_type_name<T>() -> &'static str {
+    type_name::<T>().rsplit("::").next().unwrap_or_default()
+}
+
+/// A value standing in for a required variable that is missing or invalid.
+pub(crate) trait Placeholder {
+    fn placeholder() -> Self;
+}
+
+impl<T: Default> Placeholder for T {
+    fn placeholder() -> Self {
+        T::default()
+    }
+}
+
+/// The required variables that were missing or invalid when a provider was detected.
+///
+/// Their fields hold a placeholder, which accessors must not report as a value.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Missing(BTreeSet<String>);
+
+impl Missing {
+    /// Whether the variable `name` was missing or invalid.
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.0.contains(name)
+    }
+
+    /// `value`, unless the variable `name` it was read from was missing or invalid.
+    pub(crate) fn given<T>(&self, name: &str, value: T) -> Option<T> {
+        if self.contains(name) {
+            None
+        } else {
+            Some(value)
+        }
+    }
+}
@@ -0,0 +1,118 @@
+extern crate ci_detective;
+
+mod common;
+
+use ci_detective::{Forge, GitRef, Provider, Trigger, CI};
+use common::snapshot;
+
+/// The variables AppVeyor sets on a branch build.
+const BRANCH: &[(&str, &str)] = &[
+    ("CI", "True"),
+    ("APPVEYOR", "True"),
+    ("APPVEYOR_API_URL", "http://localhost:1030/"),
+    ("APPVEYOR_ACCOUNT_NAME", "crate-ci"),
+    ("APPVEYOR_PROJECT_ID", "447561"),
+    ("APPVEYOR_PROJECT_NAME", "ci-detective"),
+    ("APPVEYOR_PROJECT_SLUG", "ci-detective"),
+    ("APPVEYOR_BUILD_FOLDER", "C:\\projects\\ci-detective"),
+    ("APPVEYOR_BUILD_ID", "21344532"),
+    ("APPVEYOR_BUILD_NUMBER", "17"),
+    ("APPVEYOR_BUILD_VERSION", "1.0.17"),
+    ("APPVEYOR_BUILD_WORKER_IMAGE", "Visual Studio 2019"),
+    ("APPVEYOR_JOB_ID", "w8v9a4rbt6ka0l0s"),
+    ("APPVEYOR_JOB_NAME", "Platform: x64"),
+    ("APPVEYOR_JOB_NUMBER", "1"),
+    ("APPVEYOR_REPO_PROVIDER", "github"),
+    ("APPVEYOR_REPO_SCM", "git"),
+    ("APPVEYOR_REPO_NAME", "crate-ci/ci-detective"),
+    ("APPVEYOR_REPO_BRANCH", "master"),
+    ("APPVEYOR_REPO_TAG", "false"),
+    (
+        "APPVEYOR_REPO_COMMIT",
+        "d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a",
+    ),
+    ("APPVEYOR_REPO_COMMIT_AUTHOR", "Jane Doe"),
+    ("APPVEYOR_REPO_COMMIT_AUTHOR_EMAIL", "jane@example.com"),
+    (
+        "APPVEYOR_REPO_COMMIT_TIMESTAMP",
+        "2018-12-30T12:00:00.0000000Z",
+    ),
+    ("APPVEYOR_REPO_COMMIT_MESSAGE", "Add AppVeyor"),
+    ("APPVEYOR_REPO_COMMIT_MESSAGE_EXTENDED", "It is a CI too."),
+    ("PLATFORM", "x64"),
+    ("CONFIGURATION", "Release"),
+];
+
+#[test]
+fn branch() {
+    let ci = CI::try_from_source(&snapshot(BRANCH, &[])).unwrap();
+    assert!(matches!(ci, CI::Appveyor(_)));
+    assert_eq!(ci.name(), "AppVeyor");
+    assert_eq!(ci.git_ref(), GitRef::Branch("master".to_owned()));
+    assert_eq!(ci.trigger(), Some(Trigger::Push));
+    assert_eq!(ci.build_number(), Some(17));
+    assert_eq!(
+        ci.build_url(),
+        Some("https://ci.appveyor.com/project/crate-ci/ci-detective/builds/21344532")
+    );
+    assert_eq!(ci.build().attempt, Some(1));
+
+    let commit = ci.commit().unwrap();
+    assert_eq!(commit.subject.as_deref(), Some("Add AppVeyor"));
+    assert_eq!(commit.body.as_deref(), Some("It is a CI too."));
+    assert_eq!(commit.author_email.as_deref(), Some("jane@example.com"));
+
+    let repo = ci.repository().unwrap();
+    assert_eq!(repo.forge, Some(Forge::GitHub));
+    assert_eq!(repo.slug(), "crate-ci/ci-detective");
+
+    let runner = ci.runner();
+    assert_eq!(runner.os, "windows");
+    assert_eq!(runner.arch, "x86_64");
+    assert_eq!(
+        ci.job().unwrap().url.as_deref(),
+        Some("https://ci.appveyor.com/project/crate-ci/ci-detective/build/job/w8v9a4rbt6ka0l0s")
+    );
+}
+
+#[test]
+fn pull_request() {
+    let env = snapshot(
+        BRANCH,
+        &[
+            ("APPVEYOR_PULL_REQUEST_NUMBER", "12"),
+            ("APPVEYOR_PULL_REQUEST_TITLE", "Add AppVeyor"),
+            (
+                "APPVEYOR_PULL_REQUEST_HEAD_REPO_NAME",
+                "contributor/ci-detective",
+            ),
+            ("APPVEYOR_PULL_REQUEST_HEAD_REPO_BRANCH", "appveyor"),
+        ],
+    );
+    let ci = CI::try_from_source(&env).unwrap();
+    assert_eq!(ci.branch(), Some("appveyor"));
+    assert_eq!(ci.trigger(), Some(Trigger::PullRequest));
+    let pr = ci.pull_request().unwrap();
+    assert_eq!(pr.number, 12);
+    assert_eq!(pr.target_branch.as_deref(), Some("master"));
+    assert_eq!(pr.is_fork, Some(true));
+}
+
+#[test]
+fn tag_and_rebuild() {
+    let env = snapshot(
+        BRANCH,
+        &[
+            ("APPVEYOR_REPO_TAG", "true"),
+            ("APPVEYOR_REPO_TAG_NAME", "v1.0.0"),
+        ],
+    );
+    let ci = CI::try_from_source(&env).unwrap();
+    assert_eq!(ci.git_ref(), GitRef::Tag("v1.0.0".to_owned()));
+    assert_eq!(ci.trigger(), Some(Trigger::Tag));
+
+    let env = snapshot(BRANCH, &[("APPVEYOR_RE_BUILD", "true")]);
+    let ci = CI::try_from_source(&env).unwrap();
+    assert_eq!(ci.trigger(), Some(Trigger::Rebuild));
+    assert_eq!(ci.build().attempt, None);
+}
@@ -0,0 +1,90 @@
+extern crate ci_detective;
+
+mod common;
+
+use ci_detective::{GitRef, Hosting, Parallelism, Provider, Trigger, CI};
+use common::snapshot;
+
+/// The variables CircleCI sets on a branch build.
+const BRANCH: &[(&str, &str)] = &[
+    ("CI", "true"),
+    ("CIRCLECI", "true"),
+    ("CIRCLE_PROJECT_USERNAME", "crate-ci"),
+    ("CIRCLE_PROJECT_REPONAME", "ci-detective"),
+    ("CIRCLE_BRANCH", "master"),
+    ("CIRCLE_SHA1", "d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a"),
+    (
+        "CIRCLE_REPOSITORY_URL",
+        "git@github.com:crate-ci/ci-detective.git",
+    ),
+    (
+        "CIRCLE_BUILD_URL",
+        "https://circleci.com/gh/crate-ci/ci-detective/91",
+    ),
+    ("CIRCLE_BUILD_NUM", "91"),
+    ("CIRCLE_ARTIFACTS", "/tmp/circleci-artifacts"),
+    ("CIRCLE_USERNAME", "jane"),
+    ("CIRCLE_TEST_REPORTS", "/tmp/circleci-test-results"),
+    ("CIRCLE_NODE_TOTAL", "4"),
+    ("CIRCLE_NODE_INDEX", "1"),
+    ("CIRCLE_JOB", "test"),
+    (
+        "CIRCLE_WORKFLOW_JOB_ID",
+        "6c7a4b1e-3f2d-4e5a-9b8c-7d6e5f4a3b2c",
+    ),
+];
+
+#[test]
+fn branch() {
+    let ci = CI::try_from_source(&snapshot(BRANCH, &[])).unwrap();
+    assert!(matches!(ci, CI::Circle(_)));
+    assert_eq!(ci.name(), "CircleCI");
+    assert_eq!(ci.git_ref(), GitRef::Branch("master".to_owned()));
+    assert_eq!(ci.trigger(), Some(Trigger::Push));
+    assert_eq!(ci.build_number(), Some(91));
+    assert_eq!(ci.repo_slug().as_deref(), Some("crate-ci/ci-detective"));
+    assert_eq!(ci.parallelism(), Some(Parallelism::new(1, 4)));
+
+    let job = ci.job().unwrap();
+    assert_eq!(job.name.as_deref(), Some("test"));
+    assert_eq!(
+        job.id.as_deref(),
+        Some("6c7a4b1e-3f2d-4e5a-9b8c-7d6e5f4a3b2c")
+    );
+
+    let runner = ci.runner();
+    assert_eq!(runner.hosting, Some(Hosting::Hosted));
+    assert_eq!(runner.image, None);
+}
+
+#[test]
+fn pull_request_from_fork() {
+    let env = snapshot(
+        BRANCH,
+        &[
+            ("CIRCLE_BRANCH", "pull/12"),
+            (
+                "CI_PULL_REQUEST",
+                "https://github.com/crate-ci/ci-detective/pull/12",
+            ),
+            ("CIRCLE_PR_USERNAME", "contributor"),
+            ("CIRCLE_PR_REPONAME", "ci-detective"),
+            ("CIRCLE_BUILD_IMAGE", "ubuntu-1404:201612-01"),
+        ],
+    );
+    let ci = CI::try_from_source(&env).unwrap();
+    assert_eq!(ci.trigger(), Some(Trigger::PullRequest));
+    let pr = ci.pull_request().unwrap();
+    assert_eq!(pr.number, 12);
+    assert_eq!(pr.head_repo.as_deref(), Some("contributor/ci-detective"));
+    assert_eq!(pr.is_fork, Some(true));
+    assert_eq!(ci.runner().image.as_deref(), Some("ubuntu-1404:201612-01"));
+}
+
+#[test]
+fn tag() {
+    let env = snapshot(BRANCH, &[("CIRCLE_BRANCH", "-"), ("CIRCLE_TAG", "v1.0.0")]);
+    let ci = CI::try_from_source(&env).unwrap();
+    assert_eq!(ci.git_ref(), GitRef::Tag("v1.0.0".to_owned()));
+    assert_eq!(ci.trigger(), Some(Trigger::Tag));
+}
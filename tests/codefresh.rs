@@ -0,0 +1,84 @@
+extern crate ci_detective;
+
+mod common;
+
+use ci_detective::{GitRef, Provider, Trigger, CI};
+use common::snapshot;
+
+/// The variables Codefresh sets on a build started by a push.
+const PUSH: &[(&str, &str)] = &[
+    ("CF_BUILD_ID", "5c2a3f8e9b1d4e0001a2b3c4"),
+    ("CF_REPO_OWNER", "crate-ci"),
+    ("CF_REPO_NAME", "ci-detective"),
+    ("CF_BRANCH", "master"),
+    ("CF_COMMIT_AUTHOR", "jane"),
+    (
+        "CF_COMMIT_URL",
+        "https://github.com/crate-ci/ci-detective/commit/d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a",
+    ),
+    ("CF_COMMIT_MESSAGE", "Add Codefresh"),
+    ("CF_REVISION", "d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a"),
+    ("CF_SHORT_REVISION", "d4b8c0f"),
+    ("CF_VOLUME_NAME", "pipeline_volume"),
+    ("CF_VOLUME_PATH", "/codefresh/volume"),
+    ("CF_BUILD_TRIGGER", "webhook"),
+    ("CF_BUILD_TIMESTAMP", "1546171200000"),
+    (
+        "CF_BUILD_URL",
+        "https://g.codefresh.io/build/5c2a3f8e9b1d4e0001a2b3c4",
+    ),
+];
+
+#[test]
+fn push() {
+    let ci = CI::try_from_source(&snapshot(PUSH, &[])).unwrap();
+    assert!(matches!(ci, CI::Codefresh(_)));
+    assert_eq!(ci.name(), "Codefresh");
+    assert_eq!(ci.git_ref(), GitRef::Branch("master".to_owned()));
+    assert_eq!(ci.trigger(), Some(Trigger::Push));
+    assert_eq!(ci.repo_slug().as_deref(), Some("crate-ci/ci-detective"));
+    assert_eq!(
+        ci.build_url(),
+        Some("https://g.codefresh.io/build/5c2a3f8e9b1d4e0001a2b3c4")
+    );
+
+    let commit = ci.commit().unwrap();
+    assert_eq!(commit.short_sha, "d4b8c0f");
+    assert_eq!(commit.subject.as_deref(), Some("Add Codefresh"));
+    assert_eq!(commit.author_name.as_deref(), Some("jane"));
+}
+
+#[test]
+fn pull_request() {
+    let env = snapshot(
+        PUSH,
+        &[
+            ("CF_BRANCH", "codefresh"),
+            ("CF_PULL_REQUEST_NUMBER", "12"),
+            ("CF_PULL_REQUEST_TARGET", "master"),
+            ("CF_PULL_REQUEST_ACTION", "opened"),
+        ],
+    );
+    let ci = CI::try_from_source(&env).unwrap();
+    assert_eq!(ci.trigger(), Some(Trigger::PullRequest));
+    let pr = ci.pull_request().unwrap();
+    assert_eq!(pr.number, 12);
+    assert_eq!(pr.source_branch.as_deref(), Some("codefresh"));
+    assert_eq!(pr.target_branch.as_deref(), Some("master"));
+}
+
+#[test]
+fn triggers() {
+    let cases = [
+        ("build", Some(Trigger::Manual)),
+        ("cli", Some(Trigger::Manual)),
+        ("cron", Some(Trigger::Schedule)),
+        ("api", Some(Trigger::Api)),
+        ("something-new", None),
+    ];
+    for &(build_trigger, trigger) in &cases {
+        let env = snapshot(PUSH, &[("CF_BUILD_TRIGGER", build_trigger)]);
+        let ci = CI::try_from_source(&env).unwrap();
+        assert_eq!(ci.trigger(), trigger, "{}", build_trigger);
+    }
+}
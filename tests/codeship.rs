@@ -0,0 +1,85 @@
+extern crate ci_detective;
+
+mod common;
+
+use ci_detective::codeship::Flavor;
+use ci_detective::{GitRef, Hosting, Provider, Trigger, CI};
+use common::snapshot;
+
+/// The variables Codeship Basic sets.
+const BASIC: &[(&str, &str)] = &[
+    ("CI", "true"),
+    ("CI_NAME", "codeship"),
+    ("CI_BRANCH", "master"),
+    ("CI_BUILD_NUMBER", "7"),
+    (
+        "CI_BUILD_URL",
+        "https://app.codeship.com/projects/123/builds/7",
+    ),
+    ("CI_COMMITTER_EMAIL", "jane@example.com"),
+    ("CI_COMMITTER_NAME", "Jane Doe"),
+    ("CI_COMMITTER_USERNAME", "jane"),
+    ("CI_COMMIT_ID", "d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a"),
+    ("CI_MESSAGE", "Add Codeship"),
+    ("CI_REPO_NAME", "crate-ci/ci-detective"),
+];
+
+/// The variables Codeship Pro sets on a pull request build.
+const PRO: &[(&str, &str)] = &[
+    ("CI", "true"),
+    ("CI_NAME", "codeship"),
+    ("CI_BRANCH", "codeship"),
+    ("CI_BUILD_ID", "0b6c6a4e-3f2d-4e5a-9b8c-7d6e5f4a3b2c"),
+    ("CI_COMMITTER_EMAIL", "jane@example.com"),
+    ("CI_COMMITTER_NAME", "Jane Doe"),
+    ("CI_COMMIT_ID", "d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a"),
+    ("CI_COMMIT_MESSAGE", "Add Codeship Pro"),
+    ("CI_PR_NUMBER", "12"),
+    (
+        "CI_PULL_REQUEST",
+        "https://github.com/crate-ci/ci-detective/pull/12",
+    ),
+];
+
+#[test]
+fn basic() {
+    let ci = CI::try_from_source(&snapshot(BASIC, &[])).unwrap();
+    let codeship = match ci {
+        CI::Codeship(ref codeship) => codeship,
+        ref other => panic!("{:?}", other),
+    };
+    assert_eq!(codeship.flavor, Flavor::Basic);
+    assert_eq!(ci.name(), "Codeship");
+    assert_eq!(ci.git_ref(), GitRef::Branch("master".to_owned()));
+    assert_eq!(ci.trigger(), Some(Trigger::Push));
+    assert_eq!(ci.build_number(), Some(7));
+    assert_eq!(ci.repo_slug().as_deref(), Some("crate-ci/ci-detective"));
+    assert_eq!(ci.runner().hosting, Some(Hosting::Hosted));
+
+    let commit = ci.commit().unwrap();
+    assert_eq!(commit.subject.as_deref(), Some("Add Codeship"));
+    assert_eq!(commit.author_name.as_deref(), Some("Jane Doe"));
+}
+
+#[test]
+fn pro_pull_request() {
+    let ci = CI::try_from_source(&snapshot(PRO, &[])).unwrap();
+    let codeship = match ci {
+        CI::Codeship(ref codeship) => codeship,
+        ref other => panic!("{:?}", other),
+    };
+    assert_eq!(codeship.flavor, Flavor::Pro);
+    assert_eq!(ci.trigger(), Some(Trigger::PullRequest));
+    let pr = ci.pull_request().unwrap();
+    assert_eq!(pr.number, 12);
+    assert_eq!(
+        pr.url.as_deref(),
+        Some("https://github.com/crate-ci/ci-detective/pull/12")
+    );
+    assert_eq!(pr.source_branch.as_deref(), Some("codeship"));
+
+    let env = snapshot(PRO, &[("CI_PR_NUMBER", "0"), ("CI_PULL_REQUEST", "false")]);
+    let ci = CI::try_from_source(&env).unwrap();
+    assert_eq!(ci.pull_request(), None);
+    assert_eq!(ci.trigger(), Some(Trigger::Push));
+}
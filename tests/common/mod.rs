@@ -0,0 +1,14 @@
+use ci_detective::Snapshot;
+
+/// `vars` with `changes` applied; a change to `"-"` removes the variable.
+pub fn snapshot(vars: &[(&str, &str)], changes: &[(&str, &str)]) -> Snapshot {
+    vars.iter()
+        .chain(changes)
+        .filter(|it| {
+            !changes
+                .iter()
+                .any(|change| change.0 == it.0 && change.1 == "-")
+        })
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "config")]
+
+extern crate ci_detective;
+
+use ci_detective::{Provider, Warning, CI};
+use std::{env, fs};
+
+const MISSING: (&str, &str) = ("CI_DETECTIVE_CONFIG", "no/such/ci-detective.toml");
+
+#[test]
+fn config_error_keeps_built_in_detection() {
+    let env = [MISSING, ("CI", "true")];
+    assert_eq!(CI::try_from_source(&env).unwrap().name(), "Generic CI");
+
+    let partial = CI::from_source_lenient(&env).unwrap();
+    assert_eq!(partial.provider.name(), "Generic CI");
+    assert!(matches!(partial.warnings[0], Warning::Config(_)));
+
+    let err = CI::try_from_source(&[MISSING]).unwrap_err();
+    assert!(err.config_error().is_some());
+    assert!(err.to_string().starts_with("no CI provider detected"));
+}
+
+#[test]
+fn from_env_rereads_the_config() {
+    let dir = env::temp_dir().join(format!("ci-detective-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str| {
+        let text = format!(
+            "name = {:?}\nmarkers = [\"CI_DETECTIVE_TEST_FARM\"]\n",
+            name
+        );
+        fs::write(dir.join("ci-detective.toml"), text).unwrap();
+    };
+    // The only test here that touches the process environment or the current directory.
+    env::set_current_dir(&dir).unwrap();
+    env::remove_var("CI_DETECTIVE_CONFIG");
+    env::remove_var("CI_DETECTIVE_PROVIDER");
+    env::set_var("CI_DETECTIVE_TEST_FARM", "1");
+
+    write("Build Farm");
+    assert_eq!(CI::from_env().unwrap().name(), "Build Farm");
+    write("Other Farm");
+    assert_eq!(CI::from_env().unwrap().name(), "Other Farm");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
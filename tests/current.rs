@@ -0,0 +1,48 @@
+extern crate ci_detective;
+
+use ci_detective::{EnvSource, Provider, CI};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NESTED: AtomicUsize = AtomicUsize::new(0);
+
+/// A custom detector that detects the CI itself, and never matches.
+fn nested(_: &dyn EnvSource) -> Option<Box<dyn Provider>> {
+    if CI::current().map(|ci| ci.build_number()) == Some(Some(7)) {
+        NESTED.fetch_add(1, Ordering::SeqCst);
+    }
+    None
+}
+
+// The only test in this file: it changes the process environment and the cache of `current`.
+#[test]
+fn current() {
+    env::remove_var("CI_DETECTIVE_CONFIG");
+    env::set_var("CI_DETECTIVE_PROVIDER", "none");
+    CI::invalidate_current();
+    assert!(CI::current().is_none());
+
+    env::set_var("CI_DETECTIVE_PROVIDER", "generic");
+    env::set_var("CI", "true");
+    env::set_var("BUILD_NUMBER", "7");
+    // Still cached.
+    assert!(CI::current().is_none());
+
+    CI::invalidate_current();
+    let ci = CI::current().unwrap();
+    assert!(matches!(ci, CI::Generic(_)));
+    assert_eq!(ci.build_number(), Some(7));
+
+    env::set_var("BUILD_NUMBER", "8");
+    assert_eq!(CI::current().unwrap().build_number(), Some(7));
+
+    // A custom detector calling `current` detects without itself, instead of deadlocking.
+    env::set_var("BUILD_NUMBER", "7");
+    CI::register(1, nested);
+    CI::invalidate_current();
+    assert_eq!(CI::current().unwrap().build_number(), Some(7));
+    assert_eq!(NESTED.load(Ordering::SeqCst), 1);
+    // The result of the outer call is cached, so the detector isn't run again.
+    assert_eq!(CI::current().unwrap().build_number(), Some(7));
+    assert_eq!(NESTED.load(Ordering::SeqCst), 1);
+}
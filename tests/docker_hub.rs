@@ -0,0 +1,44 @@
+extern crate ci_detective;
+
+mod common;
+
+use ci_detective::{GitRef, Hosting, Provider, Trigger, CI};
+use common::snapshot;
+
+/// The variables Docker Hub sets on an automated build.
+const AUTOBUILD: &[(&str, &str)] = &[
+    ("SOURCE_BRANCH", "master"),
+    ("SOURCE_COMMIT", "d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a"),
+    ("COMMIT_MSG", "Add Docker Hub"),
+    ("DOCKER_REPO", "index.docker.io/crateci/ci-detective"),
+    ("CACHE_TAG", "latest"),
+    ("IMAGE_NAME", "index.docker.io/crateci/ci-detective:latest"),
+    ("DOCKERFILE_PATH", "Dockerfile"),
+    ("BUILD_CODE", "bsdhvtsdnqyhqkfn4gqbdqj"),
+    ("SOURCE_TYPE", "git"),
+    ("DOCKER_TAG", "latest"),
+];
+
+#[test]
+fn autobuild() {
+    let ci = CI::try_from_source(&snapshot(AUTOBUILD, &[])).unwrap();
+    assert!(matches!(ci, CI::DockerHub(_)));
+    assert_eq!(ci.name(), "Docker Hub");
+    assert_eq!(ci.git_ref(), GitRef::Branch("master".to_owned()));
+    assert_eq!(ci.trigger(), Some(Trigger::Push));
+    assert_eq!(ci.build().id.as_deref(), Some("bsdhvtsdnqyhqkfn4gqbdqj"));
+    assert_eq!(
+        ci.commit().unwrap().subject.as_deref(),
+        Some("Add Docker Hub")
+    );
+
+    let runner = ci.runner();
+    assert_eq!(runner.os, "linux");
+    assert_eq!(runner.hosting, Some(Hosting::Hosted));
+}
+
+#[test]
+fn missing_markers() {
+    let env = snapshot(AUTOBUILD, &[("IMAGE_NAME", "-")]);
+    assert!(CI::from_source(&env).is_none());
+}
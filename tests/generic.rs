@@ -0,0 +1,72 @@
+extern crate ci_detective;
+
+use ci_detective::{is_ci_source, Provider, CI};
+
+#[test]
+fn fallback() {
+    let ci = CI::try_from_source(&[("CI", "true"), ("BUILD_NUMBER", "42")]).unwrap();
+    assert!(matches!(ci, CI::Generic(_)));
+    assert_eq!(ci.name(), "Generic CI");
+    assert_eq!(ci.build_number(), Some(42));
+
+    let ci = CI::try_from_source(&[("CI_NAME", "Buildkite")]).unwrap();
+    assert_eq!(ci.name(), "Buildkite");
+
+    assert!(CI::from_source(&[("CI", "false"), ("BUILD_NUMBER", "42")]).is_none());
+    assert!(CI::from_source(&[("HOME", "/root")]).is_none());
+}
+
+#[test]
+fn no_fallback_for_identified_providers() {
+    // Docker Hub's markers, but not the rest of its variables.
+    let env = [
+        ("CI", "true"),
+        ("DOCKER_REPO", "index.docker.io/crateci/ci-detective"),
+        ("IMAGE_NAME", "index.docker.io/crateci/ci-detective:latest"),
+    ];
+    let err = CI::try_from_source(&env).unwrap_err();
+    let names: Vec<_> = err.candidates().iter().map(|it| it.provider).collect();
+    // `CI` is a marker of other providers too, but only Docker Hub was identified.
+    assert!(names.contains(&"Docker Hub"), "{:?}", names);
+    assert!(CI::from_source(&env).is_none());
+
+    // One marker doesn't identify it, so this is some other CI.
+    let env = [
+        ("CI", "true"),
+        ("DOCKER_REPO", "index.docker.io/crateci/ci-detective"),
+    ];
+    assert!(matches!(CI::try_from_source(&env), Ok(CI::Generic(_))));
+}
+
+#[test]
+fn is_ci() {
+    assert!(is_ci_source(&[("CI", "true")]));
+    assert!(is_ci_source(&[("CONTINUOUS_INTEGRATION", "1")]));
+    assert!(is_ci_source(&[("RUN_ID", "5")]));
+    assert!(!is_ci_source(&[("HOME", "/root")]));
+    assert!(!is_ci_source(&[("CI", "false"), ("BUILD_ID", "5")]));
+    // A provider that fails strict detection is still a CI.
+    assert!(is_ci_source(&[
+        ("DOCKER_REPO", "index.docker.io/crateci/ci-detective"),
+        ("IMAGE_NAME", "index.docker.io/crateci/ci-detective:latest"),
+    ]));
+}
+
+#[test]
+fn provider_override_by_reported_name() {
+    let env = [
+        ("CI_NAME", "Buildkite"),
+        ("CI_DETECTIVE_PROVIDER", "buildkite"),
+    ];
+    assert!(matches!(CI::try_from_source(&env), Ok(CI::Generic(_))));
+    let env = [
+        ("CI_NAME", "Buildkite"),
+        ("CI_DETECTIVE_PROVIDER", "generic"),
+    ];
+    assert!(matches!(CI::try_from_source(&env), Ok(CI::Generic(_))));
+    let env = [
+        ("CI_NAME", "Buildkite"),
+        ("CI_DETECTIVE_PROVIDER", "gitlab"),
+    ];
+    assert!(CI::try_from_source(&env).is_err());
+}
@@ -0,0 +1,112 @@
+extern crate ci_detective;
+
+mod common;
+
+use ci_detective::{GitRef, Hosting, Provenance, Provider, Trigger, CI};
+use common::snapshot;
+
+/// The variables Jenkins sets on a build of a git branch.
+const BRANCH: &[(&str, &str)] = &[
+    ("JENKINS_URL", "https://jenkins.example.com/"),
+    ("BUILD_NUMBER", "42"),
+    ("BUILD_ID", "42"),
+    (
+        "BUILD_URL",
+        "https://jenkins.example.com/job/ci-detective/42/",
+    ),
+    ("NODE_NAME", "agent-1"),
+    ("JOB_NAME", "ci-detective"),
+    ("BUILD_TAG", "jenkins-ci-detective-42"),
+    ("EXECUTOR_NUMBER", "0"),
+    ("WORKSPACE", "/var/lib/jenkins/workspace/ci-detective"),
+    ("GIT_COMMIT", "d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a"),
+    ("GIT_URL", "https://github.com/crate-ci/ci-detective.git"),
+    ("GIT_BRANCH", "origin/master"),
+    ("BUILD_CAUSE", "SCMTRIGGER"),
+];
+
+/// The variables the GitHub pull request builder plugin adds.
+const GHPRB: &[(&str, &str)] = &[
+    (
+        "ghprbActualCommit",
+        "9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b",
+    ),
+    ("ghprbActualCommitAuthor", "Jane Doe"),
+    ("ghprbActualCommitAuthorEmail", "jane@example.com"),
+    ("ghprbPullDescription", "Adds AppVeyor"),
+    ("ghprbPullId", "12"),
+    (
+        "ghprbPullLink",
+        "https://github.com/crate-ci/ci-detective/pull/12",
+    ),
+    ("ghprbPullTitle", "Add AppVeyor"),
+    ("ghprbSourceBranch", "appveyor"),
+    ("ghprbTargetBranch", "master"),
+    ("ghprbCommentBody", ""),
+    ("sha1", "origin/pr/12/merge"),
+    ("BUILD_CAUSE", "GHPRBCAUSE"),
+    ("GIT_COMMIT", "-"),
+];
+
+#[test]
+fn branch() {
+    let ci = CI::try_from_source(&snapshot(BRANCH, &[])).unwrap();
+    assert!(matches!(ci, CI::Jenkins(_)));
+    assert_eq!(ci.name(), "Jenkins");
+    assert_eq!(ci.git_ref(), GitRef::Branch("master".to_owned()));
+    assert_eq!(ci.trigger(), Some(Trigger::Push));
+    assert_eq!(
+        ci.provenance("trigger"),
+        Some(Provenance::Var("BUILD_CAUSE".to_owned()))
+    );
+    assert_eq!(ci.build_number(), Some(42));
+    assert_eq!(ci.repo_slug().as_deref(), Some("crate-ci/ci-detective"));
+
+    let job = ci.job().unwrap();
+    assert_eq!(job.id.as_deref(), Some("jenkins-ci-detective-42"));
+    assert_eq!(job.name.as_deref(), Some("ci-detective"));
+
+    let runner = ci.runner();
+    assert_eq!(runner.name.as_deref(), Some("agent-1"));
+    assert_eq!(runner.hosting, Some(Hosting::SelfHosted));
+}
+
+#[test]
+fn causes() {
+    let cases = [
+        ("USERIDCAUSE", Trigger::Manual),
+        ("TIMERTRIGGER", Trigger::Schedule),
+        ("UPSTREAMTRIGGER", Trigger::Upstream),
+        ("REMOTECAUSE", Trigger::Api),
+        ("REPLAYCAUSE", Trigger::Rebuild),
+        ("UNKNOWNCAUSE,TIMERTRIGGER", Trigger::Schedule),
+    ];
+    for &(cause, trigger) in &cases {
+        let ci = CI::try_from_source(&snapshot(BRANCH, &[("BUILD_CAUSE", cause)])).unwrap();
+        assert_eq!(ci.trigger(), Some(trigger), "{}", cause);
+    }
+}
+
+#[test]
+fn pull_request() {
+    let ci = CI::try_from_source(&snapshot(BRANCH, GHPRB)).unwrap();
+    match ci {
+        // Set but empty when the build wasn't started by a comment.
+        CI::Jenkins(ref jenkins) => assert_eq!(jenkins.ghprb.as_ref().unwrap().comment_body, ""),
+        ref other => panic!("{:?}", other),
+    }
+    assert_eq!(ci.trigger(), Some(Trigger::PullRequest));
+    assert_eq!(ci.branch(), Some("appveyor"));
+    assert_eq!(
+        ci.commit_sha(),
+        Some("9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b")
+    );
+    assert_eq!(
+        ci.commit().unwrap().author_name.as_deref(),
+        Some("Jane Doe")
+    );
+    let pr = ci.pull_request().unwrap();
+    assert_eq!(pr.number, 12);
+    assert_eq!(pr.title.as_deref(), Some("Add AppVeyor"));
+    assert_eq!(pr.target_branch.as_deref(), Some("master"));
+}
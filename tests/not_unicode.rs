@@ -0,0 +1,91 @@
+#![cfg(unix)]
+
+extern crate ci_detective;
+
+use ci_detective::{Provider, Warning, CI};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Latin-1 for `café`, which is not valid UTF-8.
+fn latin1() -> OsString {
+    OsStr::from_bytes(b"caf\xe9").to_owned()
+}
+
+fn jenkins(changes: &[(&str, OsString)]) -> HashMap<String, OsString> {
+    let mut env: HashMap<String, OsString> = [
+        ("JENKINS_URL", "https://jenkins.example.com/"),
+        ("BUILD_NUMBER", "42"),
+        ("BUILD_ID", "42"),
+        ("BUILD_URL", "https://jenkins.example.com/job/farm/42/"),
+        ("NODE_NAME", "agent-1"),
+        ("JOB_NAME", "farm"),
+        ("BUILD_TAG", "jenkins-farm-42"),
+        ("EXECUTOR_NUMBER", "0"),
+        ("WORKSPACE", "/var/lib/jenkins/workspace/farm"),
+    ]
+    .iter()
+    .map(|&(name, value)| (name.to_owned(), value.into()))
+    .collect();
+    env.extend(
+        changes
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone())),
+    );
+    env
+}
+
+#[test]
+fn paths_are_kept() {
+    let mut workspace = OsString::from("/var/lib/jenkins/workspace/");
+    workspace.push(latin1());
+    let ci = CI::try_from_source(&jenkins(&[("WORKSPACE", workspace.clone())])).unwrap();
+    match ci {
+        CI::Jenkins(ref jenkins) => assert_eq!(jenkins.workspace, Path::new(&workspace)),
+        ref other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn values_are_reported() {
+    let env = jenkins(&[("NODE_NAME", latin1())]);
+    let err = CI::try_from_source(&env).unwrap_err();
+    assert_eq!(err.candidates()[0].not_unicode, ["NODE_NAME"]);
+    assert!(err.to_string().contains("NODE_NAME"));
+
+    let partial = CI::from_source_lenient(&env).unwrap();
+    assert_eq!(partial.provider.name(), "Jenkins");
+    assert_eq!(partial.provider.runner().name, None);
+    assert!(matches!(
+        partial.warnings[..],
+        [Warning::NotUnicode(ref name)] if name == "NODE_NAME"
+    ));
+
+    // Optional variables only warn, in lenient detection.
+    let env = jenkins(&[("GIT_BRANCH", latin1())]);
+    let ci = CI::try_from_source(&env).unwrap();
+    assert_eq!(ci.branch(), None);
+    let partial = CI::from_source_lenient(&env).unwrap();
+    assert!(matches!(
+        partial.warnings[..],
+        [Warning::NotUnicode(ref name)] if name == "GIT_BRANCH"
+    ));
+}
+
+#[test]
+fn overrides_are_reported() {
+    let env = jenkins(&[("CI_DETECTIVE_BRANCH", latin1())]);
+    let err = CI::try_from_source(&env).unwrap_err();
+    assert!(matches!(
+        err.invalid_overrides(),
+        [Warning::NotUnicode(ref name)] if name == "CI_DETECTIVE_BRANCH"
+    ));
+
+    let env = jenkins(&[("CI_DETECTIVE_PROVIDER", latin1())]);
+    let err = CI::try_from_source(&env).unwrap_err();
+    assert!(matches!(
+        err.invalid_overrides(),
+        [Warning::NotUnicode(ref name)] if name == "CI_DETECTIVE_PROVIDER"
+    ));
+}
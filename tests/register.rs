@@ -0,0 +1,91 @@
+extern crate ci_detective;
+
+use ci_detective::{EnvSource, Provider, CI};
+
+#[derive(Clone, Debug)]
+struct Farm(&'static str);
+
+impl Provider for Farm {
+    fn name(&self) -> &str {
+        self.0
+    }
+}
+
+fn detect(source: &dyn EnvSource, marker: &str, name: &'static str) -> Option<Box<dyn Provider>> {
+    source.var_os(marker)?;
+    Some(Box::new(Farm(name)))
+}
+
+fn early(source: &dyn EnvSource) -> Option<Box<dyn Provider>> {
+    detect(source, "EARLY_FARM", "Early Farm")
+}
+
+fn late(source: &dyn EnvSource) -> Option<Box<dyn Provider>> {
+    detect(source, "LATE_FARM", "Late Farm")
+}
+
+fn first(source: &dyn EnvSource) -> Option<Box<dyn Provider>> {
+    detect(source, "SAME_FARM", "First Farm")
+}
+
+fn second(source: &dyn EnvSource) -> Option<Box<dyn Provider>> {
+    detect(source, "SAME_FARM", "Second Farm")
+}
+
+/// Enough for Docker Hub to be detected strictly.
+const DOCKER_HUB: &[(&str, &str)] = &[
+    ("SOURCE_BRANCH", "master"),
+    ("SOURCE_COMMIT", "d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a"),
+    ("COMMIT_MSG", "Add Docker Hub"),
+    ("DOCKER_REPO", "index.docker.io/crateci/ci-detective"),
+    ("CACHE_TAG", "latest"),
+    ("IMAGE_NAME", "index.docker.io/crateci/ci-detective:latest"),
+];
+
+fn with(vars: &[(&'static str, &'static str)]) -> Vec<(&'static str, &'static str)> {
+    DOCKER_HUB.iter().chain(vars).cloned().collect()
+}
+
+fn name(source: &dyn EnvSource) -> Option<String> {
+    CI::from_source(source).map(|ci| ci.name().to_owned())
+}
+
+// Registration is process-wide, so every test registers what it needs; registering twice is harmless.
+
+#[test]
+fn priority() {
+    CI::register(10, early);
+    CI::register(-10, late);
+
+    let env = with(&[("EARLY_FARM", "1")]);
+    assert_eq!(name(&env).as_deref(), Some("Early Farm"));
+    assert!(matches!(CI::from_source(&env), Some(CI::Custom(_))));
+
+    let env = with(&[("LATE_FARM", "1")]);
+    assert_eq!(name(&env).as_deref(), Some("Docker Hub"));
+    // Before the generic fallback.
+    assert_eq!(
+        name(&[("CI", "true"), ("LATE_FARM", "1")]).as_deref(),
+        Some("Late Farm")
+    );
+
+    let all = CI::detect_all_from_source(&with(&[("EARLY_FARM", "1"), ("LATE_FARM", "1")]));
+    let names: Vec<_> = all.iter().map(|it| it.provider.name().to_owned()).collect();
+    assert_eq!(names, ["Early Farm", "Docker Hub", "Late Farm"]);
+}
+
+#[test]
+fn registration_order() {
+    CI::register(5, first);
+    CI::register(5, second);
+    assert_eq!(name(&[("SAME_FARM", "1")]).as_deref(), Some("First Farm"));
+}
+
+#[test]
+fn provider_override() {
+    CI::register(10, early);
+    let env = with(&[("EARLY_FARM", "1"), ("CI_DETECTIVE_PROVIDER", "docker-hub")]);
+    assert_eq!(name(&env).as_deref(), Some("Docker Hub"));
+    let env = with(&[("EARLY_FARM", "1"), ("CI_DETECTIVE_PROVIDER", "early farm")]);
+    assert_eq!(name(&env).as_deref(), Some("Early Farm"));
+}
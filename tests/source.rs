@@ -0,0 +1,56 @@
+extern crate ci_detective;
+
+use ci_detective::{EnvSource, Provider, Snapshot, CI};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+
+const ENV: &[(&str, &str)] = &[
+    ("CI", "true"),
+    ("BUILD_NUMBER", "42"),
+    ("BRANCH_NAME", "master"),
+];
+
+fn assert_detected<S: EnvSource + ?Sized>(source: &S) {
+    let ci = CI::try_from_source(source).unwrap();
+    assert!(matches!(ci, CI::Generic(_)));
+    assert_eq!(ci.build_number(), Some(42));
+    assert_eq!(ci.branch(), Some("master"));
+}
+
+#[test]
+fn maps() {
+    let map: HashMap<_, _> = ENV.iter().cloned().collect();
+    assert_detected(&map);
+    let map: HashMap<String, OsString> = ENV
+        .iter()
+        .map(|&(name, value)| (name.to_owned(), value.into()))
+        .collect();
+    assert_detected(&map);
+
+    let map: BTreeMap<_, _> = ENV.iter().cloned().collect();
+    assert_detected(&map);
+    assert_eq!(map.var_os("BUILD_ID"), None);
+}
+
+#[test]
+fn closures() {
+    let lookup = |name: &str| {
+        ENV.iter()
+            .find(|&&(key, _)| key == name)
+            .map(|&(_, value)| value.to_owned())
+    };
+    assert_detected(&lookup);
+    assert_eq!(lookup.var_os("CI"), Some(OsString::from("true")));
+    assert_eq!(lookup.var_os("BUILD_ID"), None);
+}
+
+#[test]
+fn pairs() {
+    assert_detected(ENV);
+    assert_detected(&ENV.to_vec());
+    assert_detected(&ENV.iter().cloned().collect::<Snapshot>());
+
+    // The last pair with a name wins.
+    let env = [("BUILD_NUMBER", "1"), ("BUILD_NUMBER", "42")];
+    assert_eq!(env.var_os("BUILD_NUMBER"), Some(OsString::from("42")));
+}
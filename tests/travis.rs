@@ -0,0 +1,245 @@
+extern crate ci_detective;
+
+mod common;
+
+use ci_detective::{GitRef, Provenance, Provider, Trigger, Warning, CI};
+use common::snapshot;
+
+/// The variables Travis CI sets on a pull request build.
+const PULL_REQUEST: &[(&str, &str)] = &[
+    ("CI", "true"),
+    ("TRAVIS", "true"),
+    ("CONTINUOUS_INTEGRATION", "true"),
+    ("DEBIAN_FRONTEND", "noninteractive"),
+    ("HAS_JOSH_K_SEAL_OF_APPROVAL", "true"),
+    ("TRAVIS_ALLOW_FAILURE", "false"),
+    ("TRAVIS_BRANCH", "master"),
+    (
+        "TRAVIS_BUILD_DIR",
+        "/home/travis/build/crate-ci/ci-detective",
+    ),
+    ("TRAVIS_BUILD_ID", "473021340"),
+    ("TRAVIS_BUILD_NUMBER", "58"),
+    (
+        "TRAVIS_BUILD_WEB_URL",
+        "https://travis-ci.org/crate-ci/ci-detective/builds/473021340",
+    ),
+    ("TRAVIS_COMMIT", "d4b8c0f0f4fb2a1b2fbb5a1e4a7c3f0c9d1e2f3a"),
+    ("TRAVIS_COMMIT_MESSAGE", "Add AppVeyor\n\nIt is a CI too."),
+    ("TRAVIS_COMMIT_RANGE", ""),
+    ("TRAVIS_EVENT_TYPE", "pull_request"),
+    ("TRAVIS_JOB_ID", "473021341"),
+    ("TRAVIS_JOB_NUMBER", "58.1"),
+    ("TRAVIS_OS_NAME", "linux"),
+    ("TRAVIS_OSX_IMAGE", ""),
+    ("TRAVIS_PULL_REQUEST", "12"),
+    ("TRAVIS_PULL_REQUEST_BRANCH", "appveyor"),
+    (
+        "TRAVIS_PULL_REQUEST_SHA",
+        "9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b",
+    ),
+    ("TRAVIS_PULL_REQUEST_SLUG", "contributor/ci-detective"),
+    ("TRAVIS_REPO_SLUG", "crate-ci/ci-detective"),
+    ("TRAVIS_SECURE_ENV_VARS", "false"),
+    ("TRAVIS_SUDO", "false"),
+    ("TRAVIS_TAG", ""),
+];
+
+#[test]
+fn strict() {
+    let ci = CI::try_from_source(&snapshot(PULL_REQUEST, &[])).unwrap();
+    let travis = match ci {
+        CI::Travis(ref travis) => travis,
+        ref other => panic!("{:?}", other),
+    };
+    assert_eq!(travis.build_number, 58);
+
+    assert_eq!(ci.name(), "Travis CI");
+    assert_eq!(ci.branch(), Some("appveyor"));
+    assert_eq!(ci.tag(), None);
+    assert_eq!(
+        ci.git_ref(),
+        GitRef::PullRequest {
+            number: 12,
+            source: Some("appveyor".to_owned()),
+            target: Some("master".to_owned()),
+        }
+    );
+    assert_eq!(ci.trigger(), Some(Trigger::PullRequest));
+    assert_eq!(ci.build_number(), Some(58));
+    assert_eq!(ci.repo_slug().as_deref(), Some("crate-ci/ci-detective"));
+    assert_eq!(ci.runner().os, "linux");
+    assert_eq!(ci.runner().image, None);
+
+    let commit = ci.commit().unwrap();
+    assert_eq!(commit.subject.as_deref(), Some("Add AppVeyor"));
+    assert_eq!(commit.body.as_deref(), Some("It is a CI too."));
+
+    assert_eq!(
+        ci.provenance("build_number"),
+        Some(Provenance::Var("TRAVIS_BUILD_NUMBER".to_owned()))
+    );
+    assert_eq!(ci.provenance("tag"), None);
+}
+
+#[test]
+fn push() {
+    let env = snapshot(
+        PULL_REQUEST,
+        &[
+            ("TRAVIS_EVENT_TYPE", "push"),
+            ("TRAVIS_PULL_REQUEST", "false"),
+            ("TRAVIS_PULL_REQUEST_BRANCH", ""),
+            ("TRAVIS_PULL_REQUEST_SHA", ""),
+            ("TRAVIS_PULL_REQUEST_SLUG", ""),
+            ("TRAVIS_OS_NAME", "osx"),
+            ("TRAVIS_OSX_IMAGE", "xcode12"),
+        ],
+    );
+    let ci = CI::try_from_source(&env).unwrap();
+    assert_eq!(ci.git_ref(), GitRef::Branch("master".to_owned()));
+    assert_eq!(ci.pull_request(), None);
+    assert_eq!(ci.trigger(), Some(Trigger::Push));
+    assert_eq!(ci.runner().image.as_deref(), Some("xcode12"));
+}
+
+#[test]
+fn strict_reports_missing_variables() {
+    let env = snapshot(PULL_REQUEST, &[("TRAVIS_BUILD_NUMBER", "-")]);
+    let err = CI::try_from_source(&env).unwrap_err();
+    let candidate = &err.candidates()[0];
+    assert_eq!(candidate.provider, "Travis CI");
+    assert_eq!(candidate.missing, ["TRAVIS_BUILD_NUMBER"]);
+    assert!(err.to_string().contains("TRAVIS_BUILD_NUMBER"));
+    // Travis was identified, so this is not passed off as a generic CI.
+    assert!(CI::from_source(&env).is_none());
+}
+
+#[test]
+fn strict_reports_invalid_variables() {
+    let env = snapshot(PULL_REQUEST, &[("TRAVIS_SUDO", "maybe")]);
+    let err = CI::try_from_source(&env).unwrap_err();
+    let candidate = &err.candidates()[0];
+    assert!(candidate.missing.is_empty());
+    assert_eq!(
+        candidate.invalid[0].to_string(),
+        "TRAVIS_SUDO=\"maybe\" is not bool"
+    );
+}
+
+#[test]
+fn lenient() {
+    let env = snapshot(
+        PULL_REQUEST,
+        &[("TRAVIS_BUILD_NUMBER", "-"), ("TRAVIS_SUDO", "maybe")],
+    );
+    let partial = CI::from_source_lenient(&env).unwrap();
+    assert_eq!(partial.provider.name(), "Travis CI");
+    assert!(!partial.is_complete());
+    assert!(partial.confidence < 1.0);
+    let warnings: Vec<_> = partial.warnings.iter().map(Warning::to_string).collect();
+    assert_eq!(
+        warnings,
+        [
+            "TRAVIS_BUILD_NUMBER is not set",
+            "TRAVIS_SUDO=\"maybe\" is not bool",
+        ]
+    );
+
+    // The placeholder is not reported as a value.
+    assert_eq!(partial.provider.build_number(), None);
+    assert_eq!(partial.provider.provenance("build_number"), None);
+    assert_eq!(partial.provider.branch(), Some("appveyor"));
+}
+
+#[test]
+fn detect_all() {
+    let all = CI::detect_all_from_source(&snapshot(PULL_REQUEST, &[]));
+    let names: Vec<_> = all.iter().map(|it| it.provider.name().to_owned()).collect();
+    assert_eq!(names, ["Travis CI", "Generic CI"]);
+    assert!(all[0].is_complete());
+    assert_eq!(all[0].confidence, 1.0);
+    assert_eq!(all[1].confidence, 0.0);
+
+    let env = snapshot(PULL_REQUEST, &[("TRAVIS_BUILD_NUMBER", "-")]);
+    let all = CI::detect_all_from_source(&env);
+    assert_eq!(all[0].provider.name(), "Travis CI");
+    assert!(all[0].confidence > 0.0 && all[0].confidence < 1.0);
+}
+
+#[test]
+fn overrides() {
+    let ci = CI::try_from_source(&snapshot(PULL_REQUEST, &[])).unwrap();
+    assert!(!ci.is_overridden());
+
+    let env = snapshot(PULL_REQUEST, &[("CI_DETECTIVE_BRANCH", "release")]);
+    let ci = CI::try_from_source(&env).unwrap();
+    assert!(matches!(ci, CI::Travis(_)));
+    assert!(ci.is_overridden());
+    assert_eq!(ci.branch(), Some("release"));
+    assert_eq!(ci.pull_request(), None);
+    assert_eq!(ci.git_ref(), GitRef::Branch("release".to_owned()));
+    assert_eq!(ci.trigger(), Some(Trigger::Push));
+    assert_eq!(
+        ci.provenance("branch"),
+        Some(Provenance::Override("CI_DETECTIVE_BRANCH".to_owned()))
+    );
+
+    let env = snapshot(PULL_REQUEST, &[("CI_DETECTIVE_PR", "twelve")]);
+    let err = CI::try_from_source(&env).unwrap_err();
+    assert_eq!(err.invalid_overrides().len(), 1);
+    let partial = CI::from_source_lenient(&env).unwrap();
+    assert_eq!(
+        partial.warnings[0].to_string(),
+        "CI_DETECTIVE_PR=\"twelve\" is not a pull request number"
+    );
+}
+
+#[test]
+fn provider_override() {
+    let env = snapshot(PULL_REQUEST, &[("CI_DETECTIVE_PROVIDER", "generic")]);
+    let ci = CI::try_from_source(&env).unwrap();
+    assert!(matches!(ci, CI::Generic(_)));
+
+    let env = snapshot(PULL_REQUEST, &[("CI_DETECTIVE_PROVIDER", "travis-ci")]);
+    assert!(matches!(CI::try_from_source(&env), Ok(CI::Travis(_))));
+
+    let env = snapshot(PULL_REQUEST, &[("CI_DETECTIVE_PROVIDER", "jenkins")]);
+    let err = CI::try_from_source(&env).unwrap_err();
+    assert_eq!(err.provider_override(), Some("jenkins"));
+
+    let env = snapshot(PULL_REQUEST, &[("CI_DETECTIVE_PROVIDER", "none")]);
+    assert!(CI::try_from_source(&env).is_err());
+    assert!(CI::detect_all_from_source(&env).is_empty());
+    assert!(CI::from_source_lenient(&env).is_none());
+
+    // An empty value doesn't limit detection.
+    let env = snapshot(PULL_REQUEST, &[("CI_DETECTIVE_PROVIDER", "")]);
+    assert!(matches!(CI::try_from_source(&env), Ok(CI::Travis(_))));
+}
+
+#[test]
+fn empty_values() {
+    let ci = CI::try_from_source(&snapshot(PULL_REQUEST, &[])).unwrap();
+    let travis = match ci {
+        CI::Travis(ref travis) => travis,
+        ref other => panic!("{:?}", other),
+    };
+    // Set but empty on the first build of a branch: present.
+    assert_eq!(travis.commit_range.as_deref(), Some(""));
+    // Empty when `.travis.yml` doesn't set it, or on branch builds: missing.
+    assert_eq!(travis.osx_image, None);
+    assert_eq!(travis.tag, None);
+
+    // Never empty when Travis sets it: invalid.
+    let env = snapshot(PULL_REQUEST, &[("TRAVIS_PULL_REQUEST", "")]);
+    let partial = CI::from_source_lenient(&env).unwrap();
+    let warnings: Vec<_> = partial.warnings.iter().map(Warning::to_string).collect();
+    assert_eq!(
+        warnings,
+        ["TRAVIS_PULL_REQUEST=\"\" is not allowed to be empty"]
+    );
+    assert_eq!(partial.provider.pull_request(), None);
+    // The variable is optional, so strict detection still succeeds.
+    assert!(CI::try_from_source(&env).is_ok());
+}